@@ -0,0 +1,72 @@
+// Times a full listing of a generated tree with and without --parallel.
+// Run with `cargo bench`; RTREE_BENCH_DIRS and RTREE_BENCH_FILES set the
+// size of the tree (directories per level, over two levels, and files
+// in each leaf).
+
+use std::env;
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 5;
+
+fn main() {
+    let dirs = setting("RTREE_BENCH_DIRS", 40);
+    let files = setting("RTREE_BENCH_FILES", 100);
+    let root = env::temp_dir().join(format!("rtree-bench-{}x{}x{}", dirs, dirs, files));
+    if !root.exists() {
+        generate(&root, dirs, files);
+    }
+    println!(
+        "{} directories, {} files",
+        dirs + dirs * dirs,
+        dirs * dirs * files
+    );
+
+    let sequential = time(&root, &[]);
+    let parallel = time(&root, &["--parallel"]);
+    println!("sequential  {:>10.1?}", sequential);
+    println!("--parallel  {:>10.1?}", parallel);
+    println!(
+        "speedup     {:>9.2}x",
+        sequential.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
+
+fn setting(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn generate(root: &Path, dirs: usize, files: usize) {
+    for a in 0..dirs {
+        for b in 0..dirs {
+            let dir = root.join(format!("d{}", a)).join(format!("e{}", b));
+            fs::create_dir_all(&dir).unwrap();
+            for c in 0..files {
+                File::create(dir.join(format!("f{}.txt", c))).unwrap();
+            }
+        }
+    }
+}
+
+// The best of a few runs, after one to warm the cache
+fn time(root: &Path, args: &[&str]) -> Duration {
+    let run = || {
+        let start = Instant::now();
+        let status = Command::new(env!("CARGO_BIN_EXE_rtree"))
+            .args(["--no-config", "--color=never"])
+            .args(args)
+            .arg(root)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        start.elapsed()
+    };
+    run();
+    (0..RUNS).map(|_| run()).min().unwrap()
+}
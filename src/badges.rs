@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+// Manifest file names and the project type they announce
+const MANIFESTS: [(&str, &str); 16] = [
+    ("Cargo.toml", "rust"),
+    ("package.json", "node"),
+    ("deno.json", "deno"),
+    ("go.mod", "go"),
+    ("pyproject.toml", "python"),
+    ("setup.py", "python"),
+    ("requirements.txt", "python"),
+    ("pom.xml", "java"),
+    ("build.gradle", "gradle"),
+    ("build.gradle.kts", "gradle"),
+    ("Gemfile", "ruby"),
+    ("composer.json", "php"),
+    ("mix.exs", "elixir"),
+    ("CMakeLists.txt", "cmake"),
+    ("pubspec.yaml", "dart"),
+    ("Package.swift", "swift"),
+];
+
+const LICENSE_FILES: [&str; 6] = [
+    "LICENSE",
+    "LICENSE.md",
+    "LICENSE.txt",
+    "LICENCE",
+    "COPYING",
+    "UNLICENSE",
+];
+
+// Badges like `[rust]` or `[MIT]` describing the project in `dir`
+pub fn project_badges(dir: &Path) -> Vec<String> {
+    let names: HashSet<String> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+
+    let mut badges: Vec<String> = Vec::new();
+    for (manifest, kind) in MANIFESTS {
+        if names.contains(manifest) && !badges.iter().any(|b| b == kind) {
+            badges.push(kind.to_string());
+        }
+    }
+    if names.iter().any(|name| name.ends_with(".csproj")) {
+        badges.push("dotnet".to_string());
+    }
+
+    if let Some(license) = LICENSE_FILES.iter().find(|name| names.contains(**name)) {
+        let text = fs::read_to_string(dir.join(license)).unwrap_or_default();
+        badges.push(identify_license(&text).to_string());
+    }
+
+    badges.into_iter().map(|b| format!("[{}]", b)).collect()
+}
+
+// Recognize the common licenses from their telltale phrases
+fn identify_license(text: &str) -> &'static str {
+    let text = text.to_lowercase();
+    let has = |phrase: &str| text.contains(phrase);
+
+    if has("gnu affero general public license") {
+        "AGPL-3.0"
+    } else if has("gnu lesser general public license") {
+        "LGPL"
+    } else if has("gnu general public license") {
+        if has("version 3") {
+            "GPL-3.0"
+        } else {
+            "GPL-2.0"
+        }
+    } else if has("apache license") {
+        "Apache-2.0"
+    } else if has("mozilla public license") {
+        "MPL-2.0"
+    } else if has("permission is hereby granted, free of charge") {
+        "MIT"
+    } else if has("redistribution and use in source and binary forms") {
+        if has("neither the name") {
+            "BSD-3-Clause"
+        } else {
+            "BSD-2-Clause"
+        }
+    } else if has("permission to use, copy, modify, and/or distribute") {
+        "ISC"
+    } else if has("free and unencumbered software released into the public domain") {
+        "Unlicense"
+    } else {
+        "license"
+    }
+}
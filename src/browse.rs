@@ -0,0 +1,337 @@
+use colored::*;
+use console::{Key, Term};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::i18n::{self, Msg};
+use crate::walk::{self, Node};
+use crate::{
+    branch_prefix, build_context, columns, error_note, errors, names, platform, theme, units,
+    Context, Opt,
+};
+
+// An entry of the browser. Directories are read the first time they're
+// opened.
+struct Item {
+    node: Node,
+    open: bool,
+    children: Option<Vec<Item>>,
+}
+
+impl Item {
+    fn can_open(&self) -> bool {
+        self.node.is_dir() || self.node.followed
+    }
+}
+
+// A line of the list: where its item sits in the tree, and the branch
+// glyphs in front of it
+struct Row {
+    index: Vec<usize>,
+    prefix: String,
+}
+
+struct Browser<'a> {
+    opt: &'a Opt,
+    ctx: Context,
+    items: Vec<Item>,
+    selected: usize,
+    // Row shown at the top of the list
+    scroll: usize,
+}
+
+// The alternate screen, so the shell's scrollback is left as it was.
+// Dropping it puts the terminal back, also when browsing fails or panics.
+struct Screen<'a>(&'a Term);
+
+impl Screen<'_> {
+    fn enter(term: &Term) -> io::Result<Screen<'_>> {
+        let mut out = term;
+        out.write_all(b"\x1b[?1049h")?;
+        let screen = Screen(term);
+        term.hide_cursor()?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.show_cursor();
+        let mut out = self.0;
+        let _ = out.write_all(b"\x1b[?1049l");
+    }
+}
+
+// Browse the tree below `root` in the terminal (--interactive). What
+// couldn't be read is reported once the terminal is back to normal;
+// returns false when there was any.
+pub fn browse(root: &Path, opt: &Opt) -> io::Result<bool> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return Err(io::Error::other("--interactive needs a terminal"));
+    }
+    let mut ctx = build_context(root, opt);
+    // --strict can't stop in the middle of browsing, the errors fail the
+    // run afterwards instead
+    ctx.fail_fast = None;
+    let items = load(root, 1, opt, &ctx)?;
+    let mut browser = Browser {
+        opt,
+        ctx,
+        items,
+        selected: 0,
+        scroll: 0,
+    };
+
+    let screen = Screen::enter(&term)?;
+    let result = browser.run(&term, root);
+    drop(screen);
+
+    let errors = browser.ctx.errors.into_inner().unwrap();
+    errors::emit(&errors, opt.errors);
+    result.map(|()| errors.is_empty())
+}
+
+// The entries of `dir`, as closed items
+fn load(dir: &Path, depth: usize, opt: &Opt, ctx: &Context) -> io::Result<Vec<Item>> {
+    Ok(items(walk::list_dir(dir, depth, opt, ctx)?))
+}
+
+// Directories that come with their children, walked to apply the
+// filters, don't need to be read again when opened
+fn items(nodes: Vec<Node>) -> Vec<Item> {
+    nodes
+        .into_iter()
+        .map(|mut node| {
+            let children = std::mem::take(&mut node.children);
+            Item {
+                node,
+                open: false,
+                children: (!children.is_empty()).then(|| items(children)),
+            }
+        })
+        .collect()
+}
+
+impl Browser<'_> {
+    fn run(&mut self, term: &Term, root: &Path) -> io::Result<()> {
+        let label = names::display(platform::plain_path(root).as_os_str(), self.opt.escape);
+        loop {
+            let mut rows = Vec::new();
+            list_rows(&self.items, &mut Vec::new(), &mut Vec::new(), &mut rows);
+            let last_row = rows.len().saturating_sub(1);
+            self.selected = self.selected.min(last_row);
+
+            // The root above the list, the status bar below it
+            let (height, width) = term.size();
+            let list_height = (height as usize).saturating_sub(2).max(1);
+            if self.selected < self.scroll {
+                self.scroll = self.selected;
+            } else if self.selected >= self.scroll + list_height {
+                self.scroll = self.selected + 1 - list_height;
+            }
+            self.draw(term, &label, &rows, list_height, width as usize)?;
+
+            match term.read_key()? {
+                Key::ArrowUp | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+                Key::ArrowDown | Key::Char('j') => {
+                    self.selected = (self.selected + 1).min(last_row)
+                }
+                Key::PageUp => self.selected = self.selected.saturating_sub(list_height),
+                Key::PageDown => self.selected = (self.selected + list_height).min(last_row),
+                Key::Home | Key::Char('g') => self.selected = 0,
+                Key::End | Key::Char('G') => self.selected = last_row,
+                Key::ArrowRight | Key::Char('l') | Key::Enter => {
+                    if let Some(row) = rows.get(self.selected) {
+                        self.open(&row.index);
+                    }
+                }
+                Key::ArrowLeft | Key::Char('h') => {
+                    if let Some(row) = rows.get(self.selected) {
+                        match item_mut(&mut self.items, &row.index) {
+                            Some(item) if item.open => item.open = false,
+                            // Up to the directory holding it
+                            _ => {
+                                let parent = row.index.split_last().map(|(_, parent)| parent);
+                                if let Some(i) =
+                                    rows.iter().position(|r| Some(&r.index[..]) == parent)
+                                {
+                                    self.selected = i;
+                                }
+                            }
+                        }
+                    }
+                }
+                Key::Char('q') | Key::Escape | Key::CtrlC => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    // Open the directory at `index`, reading it the first time. An open
+    // one takes the selection down to its first entry.
+    fn open(&mut self, index: &[usize]) {
+        let item = match item_mut(&mut self.items, index) {
+            Some(item) if item.can_open() => item,
+            _ => return,
+        };
+        if item.open {
+            if item.children.as_ref().is_some_and(|c| !c.is_empty()) {
+                self.selected += 1;
+            }
+            return;
+        }
+        item.open = true;
+        if item.children.is_none() {
+            let depth = item.node.depth + 1;
+            item.children = Some(
+                load(&item.node.path, depth, self.opt, &self.ctx).unwrap_or_else(|e| {
+                    item.node.error = Some(errors::describe(&e));
+                    Vec::new()
+                }),
+            );
+        }
+    }
+
+    fn draw(
+        &self,
+        term: &Term,
+        label: &str,
+        rows: &[Row],
+        list_height: usize,
+        width: usize,
+    ) -> io::Result<()> {
+        let theme = theme::get();
+        // Drawn over the last frame, clearing what's left of each line
+        let mut frame = String::from("\x1b[H");
+        let line = |text: &str, frame: &mut String| {
+            frame.push_str(&console::truncate_str(text, width, "…"));
+            frame.push_str("\x1b[K\r\n");
+        };
+
+        let help = i18n::tr(Msg::BrowseHelp).dimmed();
+        line(
+            &format!("{}  {}", theme.directory.paint(label), help),
+            &mut frame,
+        );
+        for (i, row) in rows.iter().enumerate().skip(self.scroll).take(list_height) {
+            let entry = item(&self.items, &row.index).map(|item| self.entry(item));
+            let text = format!("{}{}", row.prefix, entry.unwrap_or_default());
+            if i == self.selected {
+                let plain = console::strip_ansi_codes(&text).into_owned();
+                frame.push_str(&highlight(&plain, width));
+                frame.push_str("\r\n");
+            } else {
+                line(&text, &mut frame);
+            }
+        }
+        for _ in rows.len().saturating_sub(self.scroll)..list_height {
+            line("", &mut frame);
+        }
+
+        // The status bar, on the last line so nothing scrolls
+        let status = rows
+            .get(self.selected)
+            .and_then(|row| item(&self.items, &row.index))
+            .map(|item| self.status(&item.node))
+            .unwrap_or_default();
+        frame.push_str(&highlight(&status, width));
+        frame.push_str("\x1b[J");
+
+        let mut out = term;
+        out.write_all(frame.as_bytes())?;
+        out.flush()
+    }
+
+    // The name of an entry, colored as in the tree
+    fn entry(&self, item: &Item) -> String {
+        let node = &item.node;
+        let theme = theme::get();
+        let mut text = match &self.ctx.ls_colors {
+            Some(ls) => ls.paint(&node.name, &node.path, &node.metadata),
+            None if node.metadata.file_type().is_symlink() => theme.symlink.paint(&node.name),
+            None if node.is_dir() => theme.directory.paint(&node.name),
+            None if platform::is_executable(&node.name, &node.metadata) => {
+                theme.executable.paint(&node.name)
+            }
+            None => theme.file_style(&node.name).paint(&node.name),
+        };
+        if node.metadata.file_type().is_symlink() {
+            if let Ok(target) = platform::link_target(&node.path) {
+                let target = names::display(target.as_os_str(), self.opt.escape);
+                text.push_str(&format!(" -> {}", theme.link_target.paint(&target)));
+            }
+        }
+        if item.can_open() {
+            text.push('/');
+        }
+        text.push_str(&error_note(node.error.as_deref()));
+        text
+    }
+
+    // Type, permissions, size, modification time and path of `node`
+    fn status(&self, node: &Node) -> String {
+        let modified = match node.metadata.modified() {
+            Ok(time) => units::format_mtime(time, self.opt.timefmt.as_deref()),
+            Err(_) => "-".to_string(),
+        };
+        format!(
+            " {}  {:>10}  {}  {}",
+            columns::mode_string(&node.metadata),
+            units::human_size(node.metadata.len()),
+            modified,
+            names::display(
+                platform::plain_path(&node.path).as_os_str(),
+                self.opt.escape
+            )
+        )
+    }
+}
+
+// `text` in reverse video across the whole width, even where colors are
+// turned off: without it there's no telling where the selection is
+fn highlight(text: &str, width: usize) -> String {
+    let text = console::pad_str(text, width, console::Alignment::Left, Some("…"));
+    console::style(text)
+        .reverse()
+        .force_styling(true)
+        .to_string()
+}
+
+// The rows of the open part of the tree, in order
+fn list_rows(
+    items: &[Item],
+    prefixes: &mut Vec<bool>,
+    index: &mut Vec<usize>,
+    rows: &mut Vec<Row>,
+) {
+    for (i, item) in items.iter().enumerate() {
+        let is_last = i + 1 == items.len();
+        index.push(i);
+        rows.push(Row {
+            index: index.clone(),
+            prefix: branch_prefix(prefixes, is_last),
+        });
+        if let Some(children) = item.children.as_ref().filter(|_| item.open) {
+            prefixes.push(is_last);
+            list_rows(children, prefixes, index, rows);
+            prefixes.pop();
+        }
+        index.pop();
+    }
+}
+
+// The item at `index`, as listed by list_rows
+fn item<'a>(items: &'a [Item], index: &[usize]) -> Option<&'a Item> {
+    let (first, rest) = index.split_first()?;
+    rest.iter().try_fold(items.get(*first)?, |item, &i| {
+        item.children.as_ref()?.get(i)
+    })
+}
+
+fn item_mut<'a>(items: &'a mut [Item], index: &[usize]) -> Option<&'a mut Item> {
+    let (first, rest) = index.split_first()?;
+    rest.iter().try_fold(items.get_mut(*first)?, |item, &i| {
+        item.children.as_mut()?.get_mut(i)
+    })
+}
@@ -0,0 +1,188 @@
+use clap::Parser;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::errors::TraversalError;
+use crate::sort::SortKey;
+use crate::walk::{self, Node};
+use crate::{build_context, parse_patterns, platform, Opt};
+
+/// Walks a directory with the filters and sorting of the `rtree`
+/// command line, returning the entries instead of printing them.
+///
+/// ```no_run
+/// let tree = rtree::TreeBuilder::new("src")
+///     .max_depth(2)
+///     .ignore("target|*.o")
+///     .dirs_first(true)
+///     .build()?;
+/// for node in tree.iter() {
+///     println!("{}{}", "  ".repeat(node.depth - 1), node.name);
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct TreeBuilder {
+    root: PathBuf,
+    opt: Opt,
+}
+
+/// The entries below a root, as read by [`TreeBuilder::build`].
+pub struct Tree {
+    pub root: PathBuf,
+    /// The entries directly below the root, each with its children
+    pub nodes: Vec<Node>,
+    /// Entries that couldn't be read, which the walk went on past
+    pub errors: Vec<TraversalError>,
+}
+
+impl TreeBuilder {
+    /// A walk of `root` with the defaults of the command line, less the
+    /// config file: hidden files left out, .gitignore honored, sorted by
+    /// name.
+    pub fn new(root: impl AsRef<Path>) -> TreeBuilder {
+        TreeBuilder {
+            root: root.as_ref().to_path_buf(),
+            opt: Opt::parse_from(["rtree", "--no-config"]),
+        }
+    }
+
+    /// Descend at most `depth` levels below the root (`-L`)
+    pub fn max_depth(mut self, depth: usize) -> TreeBuilder {
+        self.opt.max_depth = Some(depth);
+        self
+    }
+
+    /// Include hidden files (`-h`)
+    pub fn show_hidden(mut self, yes: bool) -> TreeBuilder {
+        self.opt.show_hidden = yes;
+        self
+    }
+
+    /// List directories only (`-d`)
+    pub fn dirs_only(mut self, yes: bool) -> TreeBuilder {
+        self.opt.dirs_only = yes;
+        self
+    }
+
+    /// Leave out entries matching one of the `|`-separated globs (`-i`)
+    pub fn ignore(mut self, patterns: &str) -> TreeBuilder {
+        self.opt.ignore = Some(patterns.to_string());
+        self
+    }
+
+    /// Only list files matching one of the `|`-separated globs (`-P`)
+    pub fn pattern(mut self, patterns: &str) -> TreeBuilder {
+        self.opt.pattern = Some(patterns.to_string());
+        self
+    }
+
+    /// Only list files with one of these extensions (`--ext`)
+    pub fn extensions(mut self, extensions: &[&str]) -> TreeBuilder {
+        self.opt.ext = extensions.iter().map(|ext| ext.to_string()).collect();
+        self
+    }
+
+    /// Match globs and extensions regardless of case (`--ignore-case`)
+    pub fn ignore_case(mut self, yes: bool) -> TreeBuilder {
+        self.opt.ignore_case = yes;
+        self
+    }
+
+    /// Honor .gitignore, .git/info/exclude and core.excludesFile (on by
+    /// default)
+    pub fn gitignore(mut self, yes: bool) -> TreeBuilder {
+        self.opt.no_gitignore = !yes;
+        self
+    }
+
+    /// Leave out directories that are empty once filtered (`--prune`)
+    pub fn prune(mut self, yes: bool) -> TreeBuilder {
+        self.opt.prune = yes;
+        self
+    }
+
+    /// Descend into symlinks to directories (`-l`)
+    pub fn follow_links(mut self, yes: bool) -> TreeBuilder {
+        self.opt.follow = yes;
+        self
+    }
+
+    /// Stay on the root's filesystem (`-x`)
+    pub fn one_file_system(mut self, yes: bool) -> TreeBuilder {
+        self.opt.one_file_system = yes;
+        self
+    }
+
+    /// Order of the entries in each directory (`--sort`)
+    pub fn sort(mut self, key: SortKey) -> TreeBuilder {
+        self.opt.sort = key;
+        self
+    }
+
+    /// Reverse the sort (`-r`)
+    pub fn reverse(mut self, yes: bool) -> TreeBuilder {
+        self.opt.reverse = yes;
+        self
+    }
+
+    /// List directories before files (`--dirsfirst`)
+    pub fn dirs_first(mut self, yes: bool) -> TreeBuilder {
+        self.opt.dirsfirst = yes;
+        self
+    }
+
+    /// Total the size of directories into [`Node::du`] (`--du`)
+    pub fn du(mut self, yes: bool) -> TreeBuilder {
+        self.opt.du = yes;
+        self
+    }
+
+    /// Read subdirectories in parallel (`--parallel`)
+    pub fn parallel(mut self, yes: bool) -> TreeBuilder {
+        self.opt.parallel = yes;
+        self
+    }
+
+    /// Walk the tree. Fails when a glob doesn't parse or the root can't
+    /// be listed; anything below the root that can't be read ends up in
+    /// [`Tree::errors`].
+    pub fn build(&self) -> io::Result<Tree> {
+        for patterns in self.opt.ignore.iter().chain(&self.opt.pattern) {
+            parse_patterns(patterns).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+        let root = platform::long_path(&self.root);
+        let ctx = build_context(&root, &self.opt);
+        let (nodes, _) = walk::walk(&root, &self.opt, &ctx)?;
+        let mut errors = ctx.errors.into_inner().unwrap();
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Tree {
+            root,
+            nodes,
+            errors,
+        })
+    }
+}
+
+impl Tree {
+    /// Every entry, depth first, each directory before what's in it
+    pub fn iter(&self) -> Entries<'_> {
+        Entries {
+            stack: self.nodes.iter().rev().collect(),
+        }
+    }
+}
+
+/// Iterator over the entries of a [`Tree`]
+pub struct Entries<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
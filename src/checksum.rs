@@ -0,0 +1,58 @@
+use clap::ValueEnum;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::walk::Node;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+// Hex digest of the file at `path`
+pub fn digest(path: &Path, algorithm: Algorithm) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let bytes = match algorithm {
+        Algorithm::Md5 => hash::<md5::Md5>(&mut file)?,
+        Algorithm::Sha1 => hash::<sha1::Sha1>(&mut file)?,
+        Algorithm::Sha256 => hash::<sha2::Sha256>(&mut file)?,
+        Algorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(&mut file, &mut hasher)?;
+            hasher.finalize().as_bytes().to_vec()
+        }
+    };
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn hash<D: sha2::Digest + Write>(file: &mut File) -> io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    io::copy(file, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+// Digest of every regular file in the tree, hashed in parallel. Files
+// that can't be read are left out.
+pub fn digests(nodes: &[Node], algorithm: Algorithm) -> HashMap<PathBuf, String> {
+    let mut files = Vec::new();
+    collect_files(nodes, &mut files);
+    files
+        .par_iter()
+        .filter_map(|&path| Some((path.clone(), digest(path, algorithm).ok()?)))
+        .collect()
+}
+
+fn collect_files<'a>(nodes: &'a [Node], files: &mut Vec<&'a PathBuf>) {
+    for node in nodes {
+        if node.metadata.is_file() {
+            files.push(&node.path);
+        }
+        collect_files(&node.children, files);
+    }
+}
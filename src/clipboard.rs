@@ -0,0 +1,99 @@
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+// Platform clipboard tools, tried in order
+const TOOLS: [(&str, &[&str]); 5] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Put `text` on the system clipboard, falling back to the OSC 52 escape
+// sequence (which also works over SSH) when no clipboard tool is usable.
+pub fn copy(text: &str) -> io::Result<()> {
+    for (tool, args) in TOOLS {
+        if copy_with(tool, args, text).unwrap_or(false) {
+            return Ok(());
+        }
+    }
+    copy_osc52(text)
+}
+
+fn copy_with(tool: &str, args: &[&str], text: &str) -> io::Result<bool> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    Ok(child.wait()?.success())
+}
+
+fn copy_osc52(text: &str) -> io::Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+
+    // Talk to the terminal directly so redirected output stays clean
+    if let Ok(mut tty) = OpenOptions::new().write(true).open("/dev/tty") {
+        return tty.write_all(sequence.as_bytes());
+    }
+    let mut stderr = io::stderr();
+    if stderr.is_terminal() {
+        return stderr.write_all(sequence.as_bytes());
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no clipboard tool found and no terminal for OSC 52",
+    ))
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Drop ANSI escape sequences (colors, styles) from rendered output
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            // CSI sequences end with a byte in the 0x40..=0x7e range
+            for c in chars.by_ref() {
+                if ('\x40'..='\x7e').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+
+    stripped
+}
@@ -0,0 +1,69 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git;
+
+// Where GitHub looks for the file, in order
+const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+pub struct CodeOwners {
+    // One matcher per rule, since the last matching rule wins
+    rules: Vec<(Gitignore, String)>,
+    // Where the root of the tree is in the repository, as the patterns
+    // are relative to the top of it
+    prefix: PathBuf,
+}
+
+impl CodeOwners {
+    // The file at the top of the repository `root` is in, or in `root`
+    // itself outside of one
+    pub fn load(root: &Path) -> Option<CodeOwners> {
+        let (top, prefix) = git::location(root).unwrap_or_else(|| (root.into(), PathBuf::new()));
+        let text = LOCATIONS
+            .iter()
+            .find_map(|location| fs::read_to_string(top.join(location)).ok())?;
+
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let line = line.split(" #").next().unwrap_or("").trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let pattern = match fields.next() {
+                Some(pattern) => pattern,
+                None => continue,
+            };
+            let owners = fields.collect::<Vec<_>>().join(" ");
+
+            // CODEOWNERS patterns follow the gitignore rules
+            let mut builder = GitignoreBuilder::new(&top);
+            if builder.add_line(None, pattern).is_err() {
+                continue;
+            }
+            if let Ok(matcher) = builder.build() {
+                rules.push((matcher, owners));
+            }
+        }
+
+        Some(CodeOwners { rules, prefix })
+    }
+
+    // Owners of `relative` (a path below the root), None when unowned
+    pub fn owners(&self, relative: &Path, is_dir: bool) -> Option<&str> {
+        let path = self.prefix.join(relative);
+        self.rules
+            .iter()
+            .rev()
+            .find(|(matcher, _)| {
+                matcher
+                    .matched_path_or_any_parents(&path, is_dir)
+                    .is_ignore()
+            })
+            .map(|(_, owners)| owners.as_str())
+            // A pattern without owners explicitly removes ownership
+            .filter(|owners| !owners.is_empty())
+    }
+}
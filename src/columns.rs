@@ -0,0 +1,272 @@
+#[cfg(unix)]
+use std::collections::HashMap;
+use std::fs;
+#[cfg(unix)]
+use std::sync::{Mutex, OnceLock};
+
+use crate::walk::Node;
+use crate::{platform, units, Opt};
+
+// The bracketed details shown between the branch and the name, e.g.
+// `[drwxr-xr-x    4.0 KiB]  ` with -p --human-readable. Fields come in
+// the order GNU tree prints them.
+pub fn render(node: &Node, opt: &Opt) -> String {
+    let mut fields = Vec::new();
+    if opt.inodes {
+        fields.push(format!("{:>7}", inode(&node.metadata)));
+    }
+    if opt.device {
+        fields.push(format!("{:>4}", device(&node.metadata)));
+    }
+    if opt.permissions {
+        fields.push(mode_string(&node.metadata));
+    }
+    // Where ls -l puts it
+    if opt.nlink {
+        fields.push(format!("{:>3}", nlink(&node.metadata)));
+    }
+    if opt.owner {
+        fields.push(format!("{:<8}", owner(&node.metadata)));
+    }
+    if opt.group {
+        fields.push(format!("{:<8}", group(&node.metadata)));
+    }
+    if opt.human_readable {
+        fields.push(format!("{:>10}", units::human_size(node.size())));
+    } else if opt.size || opt.du {
+        fields.push(format!("{:>11}", node.size()));
+    }
+    if opt.date || opt.timefmt.is_some() {
+        let time = match node.metadata.modified() {
+            Ok(time) => units::format_mtime(time, opt.timefmt.as_deref()),
+            Err(_) => "-".to_string(),
+        };
+        fields.push(time);
+    }
+
+    if fields.is_empty() {
+        return String::new();
+    }
+    format!("[{}]  ", fields.join(" "))
+}
+
+// The character ls -F appends to the name to show the entry's type
+pub fn indicator(name: &str, metadata: &fs::Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        return "@";
+    }
+    if file_type.is_dir() {
+        return "/";
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return "|";
+        }
+        if file_type.is_socket() {
+            return "=";
+        }
+    }
+    if file_type.is_file() && platform::is_executable(name, metadata) {
+        return "*";
+    }
+
+    ""
+}
+
+// The letter `ls -l` and `find -type` use for the type: `-` for regular
+// files, then d, l, p (FIFO), s (socket), b and c (block and character
+// devices)
+pub fn type_char(file_type: fs::FileType) -> char {
+    if file_type.is_symlink() {
+        return 'l';
+    }
+    if file_type.is_dir() {
+        return 'd';
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return 'p';
+        }
+        if file_type.is_socket() {
+            return 's';
+        }
+        if file_type.is_block_device() {
+            return 'b';
+        }
+        if file_type.is_char_device() {
+            return 'c';
+        }
+    }
+
+    '-'
+}
+
+// Type and permissions as `ls -l` shows them, e.g. `drwxr-xr-x`, with
+// setuid and setgid as `s` and the sticky bit as `t` (capitals when the
+// execute bit under them is unset)
+#[cfg(unix)]
+pub fn mode_string(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let kind = type_char(metadata.file_type());
+
+    let mode = metadata.permissions().mode();
+    let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+    let special = |exec: u32, special: u32, set: char| match (mode & exec != 0, mode & special != 0)
+    {
+        (true, true) => set,
+        (false, true) => set.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    };
+
+    [
+        kind,
+        bit(0o400, 'r'),
+        bit(0o200, 'w'),
+        special(0o100, 0o4000, 's'),
+        bit(0o040, 'r'),
+        bit(0o020, 'w'),
+        special(0o010, 0o2000, 's'),
+        bit(0o004, 'r'),
+        bit(0o002, 'w'),
+        special(0o001, 0o1000, 't'),
+    ]
+    .iter()
+    .collect()
+}
+
+// Without mode bits, all there is to go on is the read-only flag
+#[cfg(not(unix))]
+pub fn mode_string(metadata: &fs::Metadata) -> String {
+    let kind = type_char(metadata.file_type());
+    let write = if metadata.permissions().readonly() {
+        "r--"
+    } else {
+        "rw-"
+    };
+    format!("{}{}", kind, write.repeat(3))
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino().to_string()
+}
+
+#[cfg(unix)]
+fn device(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev().to_string()
+}
+
+#[cfg(unix)]
+fn nlink(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink().to_string()
+}
+
+#[cfg(not(unix))]
+fn nlink(_metadata: &fs::Metadata) -> String {
+    "-".to_string()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> String {
+    "-".to_string()
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &fs::Metadata) -> String {
+    "-".to_string()
+}
+
+// Names already looked up, by uid and by gid
+#[cfg(unix)]
+static USERS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+#[cfg(unix)]
+static GROUPS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+
+// Name of the entry's owner, or the uid when it has none
+#[cfg(unix)]
+fn owner(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    cached(&USERS, metadata.uid(), |uid| unsafe {
+        lookup(
+            |entry: &mut libc::passwd, buf, len, result| {
+                libc::getpwuid_r(uid, entry, buf, len, result)
+            },
+            |entry| entry.pw_name,
+        )
+    })
+}
+
+// Name of the entry's group, or the gid when it has none
+#[cfg(unix)]
+fn group(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    cached(&GROUPS, metadata.gid(), |gid| unsafe {
+        lookup(
+            |entry: &mut libc::group, buf, len, result| {
+                libc::getgrgid_r(gid, entry, buf, len, result)
+            },
+            |entry| entry.gr_name,
+        )
+    })
+}
+
+// There are no uids to show elsewhere
+#[cfg(not(unix))]
+fn owner(_metadata: &fs::Metadata) -> String {
+    "-".to_string()
+}
+
+#[cfg(not(unix))]
+fn group(_metadata: &fs::Metadata) -> String {
+    "-".to_string()
+}
+
+#[cfg(unix)]
+fn cached(
+    names: &OnceLock<Mutex<HashMap<u32, String>>>,
+    id: u32,
+    find: impl FnOnce(u32) -> Option<String>,
+) -> String {
+    let mut names = names.get_or_init(Default::default).lock().unwrap();
+    names
+        .entry(id)
+        .or_insert_with(|| find(id).unwrap_or_else(|| id.to_string()))
+        .clone()
+}
+
+// Run one of the getpwuid_r family, growing the buffer until the entry
+// fits, and return the name it found.
+//
+// Safety: `T` has to be the C struct `call` fills in, for which all
+// zeroes is a valid value.
+#[cfg(unix)]
+unsafe fn lookup<T>(
+    mut call: impl FnMut(&mut T, *mut libc::c_char, usize, &mut *mut T) -> libc::c_int,
+    name: impl Fn(&T) -> *const libc::c_char,
+) -> Option<String> {
+    let mut entry: T = std::mem::zeroed();
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        let mut result = std::ptr::null_mut();
+        match call(&mut entry, buf.as_mut_ptr(), buf.len(), &mut result) {
+            0 if !result.is_null() => {
+                let name = std::ffi::CStr::from_ptr(name(&entry));
+                return Some(name.to_string_lossy().into_owned());
+            }
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            _ => return None,
+        }
+    }
+}
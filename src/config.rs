@@ -0,0 +1,371 @@
+use clap::Parser;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+
+use crate::glyphs::GlyphSettings;
+use crate::Opt;
+
+#[derive(Default)]
+pub struct Config {
+    // Files the settings came from, the user's before the project's
+    pub paths: Vec<PathBuf>,
+    // Named sets of flags, used with --preset NAME
+    pub presets: BTreeMap<String, Preset>,
+    pub glyphs: GlyphSettings,
+    // Theme file used unless --theme names another
+    pub theme: Option<PathBuf>,
+    pub defaults: Defaults,
+}
+
+pub struct Preset {
+    pub args: Vec<String>,
+    // File and line defining the preset
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+// Values for flags that aren't given on the command line
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    // Patterns for --ignore
+    pub ignore: Option<Vec<String>>,
+    pub color: Option<String>,
+    // --max-depth
+    pub depth: Option<usize>,
+    pub sort: Option<String>,
+}
+
+impl Defaults {
+    // The flags standing for these values
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(patterns) = &self.ignore {
+            args.push("--ignore".to_string());
+            args.push(patterns.join("|"));
+        }
+        if let Some(color) = &self.color {
+            args.push(format!("--color={}", color));
+        }
+        if let Some(depth) = self.depth {
+            args.push(format!("--max-depth={}", depth));
+        }
+        if let Some(sort) = &self.sort {
+            args.push(format!("--sort={}", sort));
+        }
+        args
+    }
+}
+
+// The file as written, before splitting presets into flags
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    presets: BTreeMap<String, Spanned<RawPreset>>,
+    #[serde(default)]
+    glyphs: GlyphSettings,
+    theme: Option<PathBuf>,
+    #[serde(default)]
+    defaults: Defaults,
+}
+
+// Either one string to split like a shell would, or a list of flags
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a string of flags or a list of them")]
+enum RawPreset {
+    Flags(String),
+    List(Vec<String>),
+}
+
+// $XDG_CONFIG_HOME/rtree/config.toml, or ~/.config/rtree/config.toml
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("rtree").join("config.toml"))
+}
+
+// The nearest .rtree.toml in the current directory or above it
+pub fn project_config_path() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(".rtree.toml"))
+        .find(|path| path.is_file())
+}
+
+// Read the user's config, then the project's on top of it. Missing
+// files are empty configs.
+pub fn load() -> Result<Config, String> {
+    let mut config = Config::default();
+    let paths = user_config_path()
+        .filter(|path| path.exists())
+        .into_iter()
+        .chain(project_config_path());
+
+    for path in paths {
+        let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let file = parse(&text, &path).map_err(|e| format!("{}:{}", path.display(), e))?;
+        config.merge(file);
+        config.paths.push(path);
+    }
+
+    Ok(config)
+}
+
+impl Config {
+    // Take the settings of `other`, keeping ours where it has none
+    fn merge(&mut self, other: Config) {
+        self.presets.extend(other.presets);
+
+        let glyphs = other.glyphs;
+        self.glyphs.branch = glyphs.branch.or(self.glyphs.branch.take());
+        self.glyphs.last = glyphs.last.or(self.glyphs.last.take());
+        self.glyphs.pipe = glyphs.pipe.or(self.glyphs.pipe.take());
+        self.glyphs.indent = glyphs.indent.or(self.glyphs.indent);
+
+        self.theme = other.theme.or(self.theme.take());
+
+        let defaults = other.defaults;
+        self.defaults.ignore = defaults.ignore.or(self.defaults.ignore.take());
+        self.defaults.color = defaults.color.or(self.defaults.color.take());
+        self.defaults.depth = defaults.depth.or(self.defaults.depth);
+        self.defaults.sort = defaults.sort.or(self.defaults.sort.take());
+    }
+}
+
+// Errors start with the line they were found on
+fn parse(text: &str, path: &Path) -> Result<Config, String> {
+    let raw: RawConfig = toml::from_str(text).map_err(|e| {
+        let line = e.span().map_or(1, |span| line_of(text, span.start));
+        format!("{}: {}", line, e.message())
+    })?;
+
+    // A relative theme path is next to the config file
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut config = Config {
+        glyphs: raw.glyphs,
+        theme: raw.theme.map(|theme| resolve(&theme, dir)),
+        defaults: raw.defaults,
+        ..Config::default()
+    };
+    for (name, preset) in raw.presets {
+        let line = line_of(text, preset.span().start);
+        let args = match preset.into_inner() {
+            RawPreset::Flags(flags) => {
+                split_args(&flags).map_err(|e| format!("{}: preset '{}': {}", line, name, e))?
+            }
+            RawPreset::List(args) => args,
+        };
+        let file = path.to_path_buf();
+        config.presets.insert(name, Preset { args, file, line });
+    }
+
+    Ok(config)
+}
+
+// `path` with a leading ~ expanded, relative to `base` otherwise
+fn resolve(path: &Path, base: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = env::var_os("HOME") {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+    base.join(path)
+}
+
+pub fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+// Split a string into words at whitespace, keeping quoted parts together
+fn split_args(flags: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+
+    for c in flags.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(format!("unterminated quote in '{}'", flags));
+    }
+    if in_word {
+        args.push(current);
+    }
+
+    Ok(args)
+}
+
+// Put the config defaults in the command line and replace `--preset
+// NAME` with the flags it stands for. Both go right after the program
+// name, presets last, so flags given explicitly still win. With
+// --no-config there is nothing to add.
+pub fn expand_args(args: Vec<OsString>) -> Result<Vec<OsString>, String> {
+    let mut no_config = false;
+    let mut names = Vec::new();
+    let mut rest = Vec::new();
+    let mut iter = args.into_iter();
+    let program = iter.next();
+
+    while let Some(arg) = iter.next() {
+        match arg.to_str() {
+            Some("--preset") => match iter.next() {
+                Some(name) => names.push(name.to_string_lossy().into_owned()),
+                None => rest.push(arg),
+            },
+            Some(flag) if flag.starts_with("--preset=") => {
+                names.push(flag["--preset=".len()..].to_string());
+            }
+            Some("--no-config") => {
+                no_config = true;
+                rest.push(arg);
+            }
+            // Everything after -- is a path
+            Some("--") => {
+                rest.push(arg);
+                rest.extend(iter.by_ref());
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    // Without presets a broken config is left for main (or `config
+    // check`) to report, and so are defaults rtree doesn't accept
+    let config = if no_config {
+        Config::default()
+    } else if names.is_empty() {
+        load().unwrap_or_default()
+    } else {
+        load()?
+    };
+    let mut expanded: Vec<OsString> = program.into_iter().collect();
+    let defaults = config.defaults.args();
+    if parse_error(&defaults).is_none() {
+        expanded.extend(defaults.into_iter().map(OsString::from));
+    }
+    for name in names {
+        let flags = config
+            .presets
+            .get(&name)
+            .ok_or(format!("unknown preset '{}'", name))?;
+        expanded.extend(flags.args.iter().map(OsString::from));
+    }
+    expanded.extend(rest);
+
+    Ok(expanded)
+}
+
+// Validate the user's config and print the settings in effect. Returns
+// whether everything checked out.
+pub fn check(no_config: bool, out: &mut dyn Write) -> io::Result<bool> {
+    if no_config {
+        writeln!(out, "Config files are ignored (--no-config)")?;
+        return Ok(true);
+    }
+
+    let config = match load() {
+        Ok(config) => config,
+        Err(e) => {
+            writeln!(out, "{}", e)?;
+            return Ok(false);
+        }
+    };
+    for path in &config.paths {
+        writeln!(out, "Config file: {}", path.display())?;
+    }
+    if config.paths.is_empty() {
+        match user_config_path() {
+            Some(path) => writeln!(out, "No config file at {}", path.display())?,
+            None => writeln!(out, "No config file (HOME is not set)")?,
+        }
+    }
+
+    let glyphs = &config.glyphs;
+    let quoted = |glyph: &Option<String>| glyph.as_ref().map(|g| format!("{:?}", g));
+    let settings = [
+        ("branch", quoted(&glyphs.branch)),
+        ("last", quoted(&glyphs.last)),
+        ("pipe", quoted(&glyphs.pipe)),
+        ("indent", glyphs.indent.map(|indent| indent.to_string())),
+    ];
+    if settings.iter().any(|(_, value)| value.is_some()) {
+        writeln!(out, "\nGlyphs:")?;
+        for (name, value) in settings {
+            if let Some(value) = value {
+                writeln!(out, "  {} = {}", name, value)?;
+            }
+        }
+    }
+
+    let mut ok = true;
+    let defaults = config.defaults.args();
+    if !defaults.is_empty() {
+        writeln!(out, "\nDefaults: {}", defaults.join(" "))?;
+        if let Some(message) = parse_error(&defaults) {
+            writeln!(out, "    {}", message)?;
+            ok = false;
+        }
+    }
+
+    if let Some(path) = &config.theme {
+        writeln!(out, "\nTheme: {}", path.display())?;
+        if let Err(e) = crate::theme::load(path) {
+            writeln!(out, "    {}", e)?;
+            ok = false;
+        }
+    }
+
+    if !config.presets.is_empty() {
+        writeln!(out, "\nPresets:")?;
+    }
+    for (name, preset) in &config.presets {
+        writeln!(out, "  {} = {}", name, preset.args.join(" "))?;
+
+        if let Some(message) = parse_error(&preset.args) {
+            let file = preset.file.display();
+            writeln!(out, "    {}:{}: {}", file, preset.line, message)?;
+            ok = false;
+        }
+    }
+
+    Ok(ok)
+}
+
+// What's wrong with `args`, which have to make sense to rtree on their own
+pub fn parse_error(args: &[String]) -> Option<String> {
+    let args = std::iter::once("rtree").chain(args.iter().map(String::as_str));
+    let message = Opt::try_parse_from(args).err()?.to_string();
+    let message = message.lines().next().unwrap_or("");
+    Some(
+        message
+            .strip_prefix("error: ")
+            .unwrap_or(message)
+            .to_string(),
+    )
+}
@@ -0,0 +1,392 @@
+use clap::ValueEnum;
+use colored::*;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::mem;
+use std::path::Path;
+
+use crate::checksum::{self, Algorithm};
+use crate::i18n::{self, Msg};
+use crate::walk::{self, Node};
+use crate::{branch_prefix, build_context, errors, names, platform, sort, theme, units, Opt};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Compare {
+    /// Files whose size changed
+    Size,
+    /// Files whose size or modification time changed
+    Mtime,
+    /// Files whose contents changed, hashing those of the same size
+    Hash,
+}
+
+// How the trees compare
+#[derive(Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Same,
+    Different,
+    // Parts of either tree couldn't be read
+    Incomplete,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Status {
+    Same,
+    Added,
+    Removed,
+    Modified,
+}
+
+// An entry of the merged tree: the node from the new tree, or from the
+// old one when it was removed
+struct Entry {
+    node: Node,
+    status: Status,
+    // What the size was before, for modified files
+    old_size: Option<u64>,
+    children: Vec<Entry>,
+    // Whether anything changed in or below it
+    changed: bool,
+}
+
+#[derive(Default)]
+struct Counts {
+    added: usize,
+    removed: usize,
+    modified: usize,
+}
+
+// Print one tree merging `old` and `new`, marking what was added (+),
+// removed (-) and modified (~). Unchanged entries are left out unless
+// `all` is set, save for the directories leading to changes. A root
+// that can't be read is an error naming it.
+pub fn diff(old: &Path, new: &Path, by: Compare, all: bool, opt: &Opt) -> io::Result<Outcome> {
+    let (old_nodes, old_errors) = walk_tree(old, opt)?;
+    let (new_nodes, new_errors) = walk_tree(new, opt)?;
+    let entries = merge(old_nodes, new_nodes, by, opt);
+
+    let mut counts = Counts::default();
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let label = |path: &Path| names::display(platform::plain_path(path).as_os_str(), opt.escape);
+    writeln!(
+        out,
+        "{} {} {}",
+        theme::get().directory.paint(&label(old)),
+        "→".dimmed(),
+        theme::get().directory.paint(&label(new))
+    )?;
+    print_entries(&entries, all, &mut Vec::new(), &mut counts, &mut out)?;
+    writeln!(
+        out,
+        "\n{}",
+        i18n::fill(
+            Msg::DiffSummary,
+            &[&counts.added, &counts.removed, &counts.modified]
+        )
+    )?;
+    out.flush()?;
+
+    Ok(if old_errors || new_errors {
+        Outcome::Incomplete
+    } else if counts.added + counts.removed + counts.modified > 0 {
+        Outcome::Different
+    } else {
+        Outcome::Same
+    })
+}
+
+// The tree below `root`, and whether anything in it couldn't be read.
+// Those errors are all reported: --strict stopping at the first one
+// would exit as if the trees differed.
+fn walk_tree(root: &Path, opt: &Opt) -> io::Result<(Vec<Node>, bool)> {
+    let root = platform::long_path(root);
+    let mut ctx = build_context(&root, opt);
+    ctx.fail_fast = None;
+    let (nodes, _) = walk::walk(&root, opt, &ctx).map_err(|e| {
+        let path = names::display(platform::plain_path(&root).as_os_str(), opt.escape);
+        io::Error::new(e.kind(), format!("{}: {}", path, errors::describe(&e)))
+    })?;
+    let errors = ctx.errors.into_inner().unwrap();
+    errors::emit(&errors, opt.errors);
+    Ok((nodes, !errors.is_empty()))
+}
+
+// Pair up the entries of a directory in both trees by name and compare
+// them, in the order of the walk. Names are compared as stored, as two
+// can print the same.
+fn merge(old: Vec<Node>, new: Vec<Node>, by: Compare, opt: &Opt) -> Vec<Entry> {
+    let mut old: HashMap<OsString, Node> = old
+        .into_iter()
+        .map(|node| (file_name(&node), node))
+        .collect();
+    let mut pairs: Vec<(Option<Node>, Option<Node>)> = new
+        .into_iter()
+        .map(|node| (old.remove(&file_name(&node)), Some(node)))
+        .collect();
+    pairs.extend(old.into_values().map(|node| (Some(node), None)));
+
+    let mut entries: Vec<Entry> = pairs
+        .into_par_iter()
+        .flat_map_iter(|pair| match pair {
+            (Some(mut old), Some(mut new)) if is_dir(&old) && is_dir(&new) => {
+                let children = merge(
+                    mem::take(&mut old.children),
+                    mem::take(&mut new.children),
+                    by,
+                    opt,
+                );
+                vec![Entry {
+                    changed: children.iter().any(|child| child.changed),
+                    node: new,
+                    status: Status::Same,
+                    old_size: None,
+                    children,
+                }]
+            }
+            // A file that became a directory or the other way round
+            (Some(old), Some(new)) if is_dir(&old) != is_dir(&new) => {
+                vec![whole(old, Status::Removed), whole(new, Status::Added)]
+            }
+            (Some(old), Some(new)) => {
+                let status = if modified(&old, &new, by) {
+                    Status::Modified
+                } else {
+                    Status::Same
+                };
+                let old_size = Some(old.metadata.len())
+                    .filter(|&size| status == Status::Modified && size != new.metadata.len());
+                vec![Entry {
+                    changed: status != Status::Same,
+                    node: new,
+                    status,
+                    old_size,
+                    children: Vec::new(),
+                }]
+            }
+            (Some(old), None) => vec![whole(old, Status::Removed)],
+            (None, Some(new)) => vec![whole(new, Status::Added)],
+            (None, None) => Vec::new(),
+        })
+        .collect();
+    entries.sort_by(|a, b| sort::compare_nodes(&a.node, &b.node, opt));
+    entries
+}
+
+fn file_name(node: &Node) -> OsString {
+    node.path.file_name().unwrap_or_default().to_os_string()
+}
+
+fn is_dir(node: &Node) -> bool {
+    node.is_dir() || node.followed
+}
+
+// An entry only in one of the trees, with everything below it
+fn whole(mut node: Node, status: Status) -> Entry {
+    let children = mem::take(&mut node.children)
+        .into_iter()
+        .map(|child| whole(child, status))
+        .collect();
+    Entry {
+        node,
+        status,
+        old_size: None,
+        children,
+        changed: true,
+    }
+}
+
+// Whether a file present in both trees changed, going by `by`. Links
+// changed when they point elsewhere.
+fn modified(old: &Node, new: &Node, by: Compare) -> bool {
+    let (old_type, new_type) = (old.metadata.file_type(), new.metadata.file_type());
+    if old_type.is_symlink() || new_type.is_symlink() {
+        return old_type.is_symlink() != new_type.is_symlink()
+            || platform::link_target(&old.path).ok() != platform::link_target(&new.path).ok();
+    }
+    if old.metadata.len() != new.metadata.len() {
+        return true;
+    }
+    match by {
+        Compare::Size => false,
+        Compare::Mtime => old.metadata.modified().ok() != new.metadata.modified().ok(),
+        // Files that can't be read count as changed
+        Compare::Hash => {
+            let old_digest = checksum::digest(&old.path, Algorithm::Blake3).ok();
+            let new_digest = checksum::digest(&new.path, Algorithm::Blake3).ok();
+            old_digest.is_none() || old_digest != new_digest
+        }
+    }
+}
+
+fn print_entries(
+    entries: &[Entry],
+    all: bool,
+    prefixes: &mut Vec<bool>,
+    counts: &mut Counts,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let shown: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| all || entry.changed)
+        .collect();
+    for (i, entry) in shown.iter().enumerate() {
+        let is_last = i + 1 == shown.len();
+        let name = &entry.node.name;
+        let (marker, name) = match entry.status {
+            Status::Added => {
+                counts.added += 1;
+                ("+".green(), name.green().to_string())
+            }
+            Status::Removed => {
+                counts.removed += 1;
+                ("-".red(), name.red().to_string())
+            }
+            Status::Modified => {
+                counts.modified += 1;
+                ("~".yellow(), name.yellow().to_string())
+            }
+            Status::Same if is_dir(&entry.node) => {
+                (" ".normal(), theme::get().directory.paint(name))
+            }
+            Status::Same => (" ".normal(), name.clone()),
+        };
+        let detail = match entry.old_size {
+            Some(old_size) => format!(
+                "  {}",
+                format!(
+                    "({} → {})",
+                    units::human_size(old_size),
+                    units::human_size(entry.node.metadata.len())
+                )
+                .dimmed()
+            ),
+            None => String::new(),
+        };
+        writeln!(
+            out,
+            "{} {}{}{}",
+            marker,
+            branch_prefix(prefixes, is_last),
+            name,
+            detail
+        )?;
+
+        prefixes.push(is_last);
+        print_entries(&entry.children, all, prefixes, counts, out)?;
+        prefixes.pop();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::names;
+    use clap::Parser;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A fresh directory holding `files` (name and contents), where a
+    // name ending in '/' is a directory
+    fn tree(files: &[(&[u8], &str)]) -> PathBuf {
+        // Tests run in parallel, each with trees of their own
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let root =
+            std::env::temp_dir().join(format!("rtree-diff-{}-{}", std::process::id(), count));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for (file, contents) in files {
+            match file.strip_suffix(b"/") {
+                Some(dir) => fs::create_dir_all(root.join(names::from_bytes(dir))).unwrap(),
+                None => fs::write(root.join(names::from_bytes(file)), contents).unwrap(),
+            }
+        }
+        root
+    }
+
+    // The merged entries as `status name` lines, children indented
+    fn compare(old: &[(&[u8], &str)], new: &[(&[u8], &str)], by: Compare) -> Vec<String> {
+        let opt = Opt::parse_from(["rtree"]);
+        let (old, new) = (tree(old), tree(new));
+        let (old_nodes, _) = walk_tree(&old, &opt).unwrap();
+        let (new_nodes, _) = walk_tree(&new, &opt).unwrap();
+        let mut lines = Vec::new();
+        show(&merge(old_nodes, new_nodes, by, &opt), 0, &mut lines);
+        fs::remove_dir_all(old).unwrap();
+        fs::remove_dir_all(new).unwrap();
+        lines
+    }
+
+    fn show(entries: &[Entry], depth: usize, lines: &mut Vec<String>) {
+        for entry in entries {
+            let status = match entry.status {
+                Status::Same if entry.changed => "*",
+                Status::Same => "=",
+                Status::Added => "+",
+                Status::Removed => "-",
+                Status::Modified => "~",
+            };
+            lines.push(format!(
+                "{}{} {}",
+                "  ".repeat(depth),
+                status,
+                entry.node.name
+            ));
+            show(&entry.children, depth + 1, lines);
+        }
+    }
+
+    #[test]
+    fn merges_trees() {
+        let old: &[(&[u8], &str)] = &[
+            (b"gone.txt", ""),
+            (b"same.txt", "same"),
+            (b"src/", ""),
+            (b"src/main.rs", "fn main() {}"),
+            (b"swapped", "a file"),
+        ];
+        let new: &[(&[u8], &str)] = &[
+            (b"new.txt", ""),
+            (b"same.txt", "same"),
+            (b"src/", ""),
+            (b"src/main.rs", "fn main() { run() }"),
+            (b"swapped/", ""),
+        ];
+        assert_eq!(
+            compare(old, new, Compare::Size),
+            [
+                "- gone.txt",
+                "+ new.txt",
+                "= same.txt",
+                "* src",
+                "  ~ main.rs",
+                "- swapped",
+                "+ swapped",
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_by_hash() {
+        let old: &[(&[u8], &str)] = &[(b"a", "one"), (b"b", "two")];
+        let new: &[(&[u8], &str)] = &[(b"a", "one"), (b"b", "owt")];
+        assert_eq!(compare(old, new, Compare::Size), ["= a", "= b"]);
+        assert_eq!(compare(old, new, Compare::Hash), ["= a", "~ b"]);
+    }
+
+    // A backslash sequence and the byte it stands for print the same
+    #[cfg(unix)]
+    #[test]
+    fn pairs_names_by_their_bytes() {
+        let old: &[(&[u8], &str)] = &[(b"caf\\xe9", "")];
+        let new: &[(&[u8], &str)] = &[(b"caf\xe9", "")];
+        assert_eq!(
+            compare(old, new, Compare::Size),
+            ["- caf\\xe9", "+ caf\\xe9"]
+        );
+    }
+}
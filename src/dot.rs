@@ -0,0 +1,54 @@
+use std::io::{self, Write};
+
+use crate::walk::Node;
+
+// Write the tree as a Graphviz digraph: one node per entry, an edge
+// from every directory to what it contains
+pub fn write_graph(root_label: &str, nodes: &[Node], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "digraph tree {{")?;
+    writeln!(out, "  rankdir=LR;")?;
+    writeln!(out, "  node [fontname=\"monospace\"];")?;
+    writeln!(out, "  n0 [label={}, shape=folder];", quote(root_label))?;
+    let mut next_id = 1;
+    write_nodes(nodes, 0, &mut next_id, out)?;
+    writeln!(out, "}}")
+}
+
+fn write_nodes(
+    nodes: &[Node],
+    parent: usize,
+    next_id: &mut usize,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for node in nodes {
+        let id = *next_id;
+        *next_id += 1;
+
+        let shape = if node.is_dir() || node.followed {
+            "folder"
+        } else {
+            "note"
+        };
+        writeln!(
+            out,
+            "  n{} [label={}, shape={}];",
+            id,
+            quote(&node.name),
+            shape
+        )?;
+        writeln!(out, "  n{} -> n{};", parent, id)?;
+
+        write_nodes(&node.children, id, next_id, out)?;
+    }
+    Ok(())
+}
+
+// `s` as a DOT string
+fn quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
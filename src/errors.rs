@@ -0,0 +1,75 @@
+use clap::ValueEnum;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{json, names};
+
+// Exit codes besides 0 for success. A check finding differences
+// (`inject --check`, `manifest verify`, `diff`, `config check`) shares
+// 1 with entries that couldn't be read or written, as with diff(1).
+pub const EXIT_TRAVERSAL: i32 = 1;
+pub const EXIT_CHECK_FAILED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NO_MATCH: i32 = 3;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ErrorFormat {
+    /// `rtree: PATH: MESSAGE` lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Something that went wrong while walking, without stopping the walk
+#[derive(Clone)]
+pub struct TraversalError {
+    pub path: PathBuf,
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl TraversalError {
+    pub fn io(path: &Path, error: &io::Error) -> TraversalError {
+        let kind = match error.kind() {
+            io::ErrorKind::PermissionDenied => "permission_denied",
+            io::ErrorKind::NotFound => "not_found",
+            _ => "io",
+        };
+        TraversalError {
+            path: path.to_path_buf(),
+            kind,
+            message: error.to_string(),
+        }
+    }
+}
+
+// What went wrong, without the `(os error 13)` that io::Error adds
+pub fn describe(error: &io::Error) -> String {
+    let message = error.to_string();
+    match message.rfind(" (os error ") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
+}
+
+// Print the errors on stderr, keeping stdout for the tree
+pub fn emit(errors: &[TraversalError], format: ErrorFormat) {
+    for error in errors {
+        match format {
+            ErrorFormat::Text => {
+                eprintln!("rtree: {}: {}", path(error), error.message)
+            }
+            ErrorFormat::Json => eprintln!(
+                "{{\"path\":{},\"kind\":{},\"message\":{}}}",
+                json::string(&path(error)),
+                json::string(error.kind),
+                json::string(&error.message)
+            ),
+        }
+    }
+}
+
+// The path of `error` without losing bytes that aren't UTF-8
+fn path(error: &TraversalError) -> String {
+    names::display(error.path.as_os_str(), false)
+}
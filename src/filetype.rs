@@ -0,0 +1,142 @@
+use colored::Color;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Interpreter named by the `#!` line of an extensionless script, e.g.
+// "python" for `#!/usr/bin/env python3`. Files with an extension are
+// classified by that instead and not opened.
+pub fn shebang_interpreter(path: &Path, name: &str) -> Option<String> {
+    if name.trim_start_matches('.').contains('.') {
+        return None;
+    }
+
+    let mut head = [0u8; 128];
+    let len = File::open(path).and_then(|mut f| f.read(&mut head)).ok()?;
+    let line = head[..len].strip_prefix(b"#!")?;
+    let line = String::from_utf8_lossy(line);
+    let line = line.lines().next()?;
+
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    // `env` runs the next word that isn't an option or assignment
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+
+    // python3.12 and python are the same kind of script
+    let base = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    Some(if base.is_empty() { program } else { base }.to_string())
+}
+
+// Color used for scripts run by `interpreter`
+pub fn interpreter_color(interpreter: &str) -> Color {
+    match interpreter {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => Color::BrightGreen,
+        "python" | "pypy" => Color::BrightBlue,
+        "node" | "deno" | "bun" => Color::BrightYellow,
+        "ruby" => Color::BrightRed,
+        "perl" => Color::BrightMagenta,
+        _ => Color::Cyan,
+    }
+}
+
+// Number of lines in the file at `path`, None for binaries (anything
+// with a NUL byte in its first 8 KiB) and files that can't be read
+pub fn line_count(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut lines = 0;
+    let mut first = true;
+    let mut last = b'\n';
+    loop {
+        let len = file.read(&mut buf).ok()?;
+        if len == 0 {
+            break;
+        }
+        if first && buf[..len.min(8192)].contains(&0) {
+            return None;
+        }
+        first = false;
+        lines += buf[..len].iter().filter(|&&b| b == b'\n').count() as u64;
+        last = buf[len - 1];
+    }
+    // A last line without a newline still counts
+    if last != b'\n' {
+        lines += 1;
+    }
+    Some(lines)
+}
+
+// Magic bytes at the start of a file, and what they make it
+const SIGNATURES: [(&[u8], &str); 24] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"\x00\x00\x01\x00", "image/vnd.microsoft.icon"),
+    (b"II*\x00", "image/tiff"),
+    (b"MM\x00*", "image/tiff"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"BZh", "application/x-bzip2"),
+    (b"\xfd7zXZ\x00", "application/x-xz"),
+    (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"Rar!\x1a\x07", "application/vnd.rar"),
+    (b"\x7fELF", "application/x-executable"),
+    (b"MZ", "application/vnd.microsoft.portable-executable"),
+    (b"\x00asm", "application/wasm"),
+    (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (b"ID3", "audio/mpeg"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x1aE\xdf\xa3", "video/webm"),
+];
+
+// MIME type of the file at `path` judging by its first bytes rather
+// than its name, None when it can't be read
+pub fn mime_type(path: &Path) -> Option<&'static str> {
+    let mut head = [0u8; 512];
+    let len = File::open(path).and_then(|mut f| f.read(&mut head)).ok()?;
+    let head = &head[..len];
+    if head.is_empty() {
+        return Some("inode/x-empty");
+    }
+
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return Some(mime);
+    }
+    // Containers with the kind of content further in
+    match (head.get(..4), head.get(8..12)) {
+        (Some(b"RIFF"), Some(b"WEBP")) => return Some("image/webp"),
+        (Some(b"RIFF"), Some(b"WAVE")) => return Some("audio/wav"),
+        (Some(b"RIFF"), Some(b"AVI ")) => return Some("video/x-msvideo"),
+        (_, _) => {}
+    }
+    if head.get(4..8) == Some(b"ftyp") {
+        return Some("video/mp4");
+    }
+    if head.get(257..262) == Some(b"ustar") {
+        return Some("application/x-tar");
+    }
+
+    // Text, told apart by how it starts
+    if head.contains(&0) || std::str::from_utf8(head).is_err_and(|e| e.error_len().is_some()) {
+        return Some("application/octet-stream");
+    }
+    let text = String::from_utf8_lossy(head).trim_start().to_lowercase();
+    Some(if text.starts_with("#!") {
+        "text/x-script"
+    } else if text.starts_with("<!doctype html") || text.starts_with("<html") {
+        "text/html"
+    } else if text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg")) {
+        "image/svg+xml"
+    } else if text.starts_with("<?xml") {
+        "text/xml"
+    } else {
+        "text/plain"
+    })
+}
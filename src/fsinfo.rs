@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Mount points of kernel pseudo filesystems that make no sense to walk
+const VIRTUAL_DIRS: [&str; 4] = ["/proc", "/sys", "/dev", "/run"];
+
+// statfs(2) magic numbers of the same kind of filesystems, for when
+// they are mounted somewhere else (containers, chroots)
+#[cfg(target_os = "linux")]
+const VIRTUAL_MAGICS: [u64; 9] = [
+    0x9fa0,     // proc
+    0x62656572, // sysfs
+    0x1cd1,     // devpts
+    0x64626720, // debugfs
+    0x74726163, // tracefs
+    0x27e0eb,   // cgroup
+    0x63677270, // cgroup2
+    0x73636673, // securityfs
+    0x6165676c, // pstore
+];
+
+// Whether `dir` is (the mount point of) a virtual filesystem
+pub fn is_virtual(dir: &Path) -> bool {
+    if let Ok(canonical) = fs::canonicalize(dir) {
+        if VIRTUAL_DIRS.iter().any(|d| canonical == Path::new(d)) {
+            return true;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(magic) = fs_magic(dir) {
+        return VIRTUAL_MAGICS.contains(&magic);
+    }
+
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn fs_magic(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut buf) } != 0 {
+        return None;
+    }
+    Some(buf.f_type as u64)
+}
+
+// Filesystem type of every mount point, from /proc/self/mountinfo
+pub fn mount_types() -> HashMap<PathBuf, String> {
+    let mut types = HashMap::new();
+    let text = match fs::read_to_string("/proc/self/mountinfo") {
+        Ok(text) => text,
+        Err(_) => return types,
+    };
+
+    for line in text.lines() {
+        // The mount point is the 5th field, the type follows the " - "
+        let (fields, rest) = match line.split_once(" - ") {
+            Some(split) => split,
+            None => continue,
+        };
+        let mount_point = match fields.split(' ').nth(4) {
+            Some(mount_point) => unescape_mountinfo(mount_point),
+            None => continue,
+        };
+        if let Some(fs_type) = rest.split(' ').next() {
+            types.insert(PathBuf::from(mount_point), fs_type.to_string());
+        }
+    }
+
+    types
+}
+
+// mountinfo escapes space, tab, newline and backslash as octal (\040)
+fn unescape_mountinfo(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(code) = u8::from_str_radix(digits, 8) {
+                result.push(code);
+                i += 4;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+pub struct Usage {
+    pub total: u64,
+    pub used: u64,
+    pub free: u64,
+    pub inodes: u64,
+}
+
+// Space and inode usage of the filesystem holding `path`. The widths of
+// the statvfs fields differ between platforms, hence the casts.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+pub fn usage(path: &Path) -> Option<Usage> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut buf) } != 0 {
+        return None;
+    }
+
+    let block = buf.f_frsize as u64;
+    Some(Usage {
+        total: buf.f_blocks as u64 * block,
+        used: (buf.f_blocks as u64 - buf.f_bfree as u64) * block,
+        // What unprivileged users can still write
+        free: buf.f_bavail as u64 * block,
+        inodes: buf.f_files as u64,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn usage(_path: &Path) -> Option<Usage> {
+    None
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::names;
+
+// The top of the repository `dir` is in, and where `dir` is below it.
+// None when `dir` isn't inside a repository.
+pub fn location(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--show-toplevel", "--show-prefix"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let mut lines = output.stdout.split(|&b| b == b'\n');
+    let top = names::from_bytes(lines.next()?);
+    let prefix = names::from_bytes(lines.next().unwrap_or_default());
+    Some((PathBuf::from(top), PathBuf::from(prefix)))
+}
+
+// Time of the last commit touching each file below `root`, keyed by the
+// path relative to `root`. Empty when `root` isn't inside a repository.
+pub fn last_commit_times(root: &Path) -> HashMap<PathBuf, i64> {
+    let mut times = HashMap::new();
+
+    // Newest commits come first, so the first time a path shows up is
+    // its last change
+    let output = match Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "log",
+            "--format=@%ct",
+            "--name-only",
+            "-z",
+            "--relative",
+            "--",
+            ".",
+        ])
+        .output()
+    {
+        Ok(output) if output.status.success() => output.stdout,
+        _ => return times,
+    };
+
+    // With -z the names come as they are, rather than quoted when they
+    // aren't plain ASCII, each ended by a NUL. The first name of a
+    // commit follows its time on a line of its own.
+    let mut commit_time = 0;
+    for record in output.split(|&b| b == 0) {
+        let record = record.strip_prefix(b"\n").unwrap_or(record);
+        let time = record
+            .strip_prefix(b"@")
+            .and_then(|time| std::str::from_utf8(time).ok())
+            .and_then(|time| time.parse().ok());
+        if let Some(time) = time {
+            commit_time = time;
+        } else if !record.is_empty() {
+            let path = PathBuf::from(names::from_bytes(record));
+            times.entry(path).or_insert(commit_time);
+        }
+    }
+
+    times
+}
+
+// Two-letter `git status --short` code (e.g. "M ", " M", "??", "!!") of
+// every changed, untracked or ignored path below `root`, keyed by the
+// path relative to `root`. Empty when `root` isn't inside a repository.
+pub fn statuses(root: &Path) -> HashMap<PathBuf, String> {
+    let mut statuses = HashMap::new();
+
+    // Status paths are relative to the top of the repository
+    let prefix = match Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "--show-prefix"])
+        .output()
+    {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string(),
+        _ => return statuses,
+    };
+    let output = match Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--ignored",
+            "--untracked-files=all",
+            "--",
+            ".",
+        ])
+        .output()
+    {
+        Ok(output) if output.status.success() => output.stdout,
+        _ => return statuses,
+    };
+
+    let output = String::from_utf8_lossy(&output);
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let (code, path) = record.split_at(2);
+        // Renames and copies are followed by the path they came from
+        if code.contains(['R', 'C']) {
+            records.next();
+        }
+        let path = path[1..].trim_end_matches('/');
+        if let Some(relative) = path.strip_prefix(prefix.as_str()) {
+            statuses.insert(PathBuf::from(relative), code.to_string());
+        }
+    }
+
+    statuses
+}
+
+// Status of `relative`, or of the untracked or ignored directory it's in
+pub fn status_of<'a>(statuses: &'a HashMap<PathBuf, String>, relative: &Path) -> Option<&'a str> {
+    if let Some(code) = statuses.get(relative) {
+        return Some(code);
+    }
+    relative
+        .ancestors()
+        .skip(1)
+        .filter_map(|dir| statuses.get(dir))
+        .find(|code| *code == "??" || *code == "!!")
+        .map(String::as_str)
+}
@@ -0,0 +1,101 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Charset {
+    /// Box-drawing characters
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    /// Plain `|--` and `` `-- ``, for consoles that mangle Unicode
+    Ascii,
+}
+
+// The pieces branch prefixes are built from
+pub struct Glyphs {
+    // In front of an entry with more entries after it
+    pub branch: String,
+    // In front of the last entry of a directory
+    pub last: String,
+    // Below an entry with more entries after it
+    pub pipe: String,
+    // Below the last entry
+    pub blank: String,
+}
+
+// Replacements for the glyphs of the charset, from flags or the
+// [glyphs] table of the config file
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GlyphSettings {
+    pub branch: Option<String>,
+    pub last: Option<String>,
+    pub pipe: Option<String>,
+    // Columns taken by each level
+    pub indent: Option<usize>,
+}
+
+static GLYPHS: OnceLock<Glyphs> = OnceLock::new();
+
+pub fn init(charset: Charset, settings: &GlyphSettings) {
+    let mut glyphs = for_charset(charset);
+    if let Some(branch) = &settings.branch {
+        glyphs.branch = branch.clone();
+    }
+    if let Some(last) = &settings.last {
+        glyphs.last = last.clone();
+    }
+    if let Some(pipe) = &settings.pipe {
+        glyphs.pipe = pipe.clone();
+    }
+
+    match settings.indent {
+        Some(width) => {
+            let width = width.max(1);
+            glyphs.branch = fit(&glyphs.branch, width);
+            glyphs.last = fit(&glyphs.last, width);
+            glyphs.pipe = fit(&glyphs.pipe, width);
+            glyphs.blank = " ".repeat(width);
+        }
+        // Below the last entry, line up with the others
+        None => glyphs.blank = " ".repeat(glyphs.pipe.chars().count()),
+    }
+
+    let _ = GLYPHS.set(glyphs);
+}
+
+// Pad `glyph` with spaces to `width` columns, or cut it short keeping a
+// space before the name
+fn fit(glyph: &str, width: usize) -> String {
+    let chars: Vec<char> = glyph.chars().collect();
+    if chars.len() <= width {
+        return format!("{:<width$}", glyph, width = width);
+    }
+    if width == 1 {
+        return chars[0].to_string();
+    }
+    let mut fitted: String = chars[..width - 1].iter().collect();
+    fitted.push(' ');
+    fitted
+}
+
+pub fn get() -> &'static Glyphs {
+    GLYPHS.get_or_init(|| for_charset(Charset::Utf8))
+}
+
+fn for_charset(charset: Charset) -> Glyphs {
+    match charset {
+        Charset::Utf8 => Glyphs {
+            branch: "├── ".to_string(),
+            last: "└── ".to_string(),
+            pipe: "│   ".to_string(),
+            blank: "    ".to_string(),
+        },
+        Charset::Ascii => Glyphs {
+            branch: "|-- ".to_string(),
+            last: "`-- ".to_string(),
+            pipe: "|   ".to_string(),
+            blank: "    ".to_string(),
+        },
+    }
+}
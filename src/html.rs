@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::walk::Node;
+use crate::xml::escape;
+use crate::{branch_prefix, names, report_line, Stats};
+
+// Write the tree as a static HTML page, every entry linking to its path
+// below `base_href`. The report goes at the bottom unless `stats` is None.
+pub fn write_tree(
+    root_label: &str,
+    root: Option<&Path>,
+    nodes: &[Node],
+    stats: Option<&Stats>,
+    base_href: &str,
+    title: &str,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let base = base_href.trim_end_matches('/');
+    let title = escape(title);
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", title)?;
+    writeln!(
+        out,
+        "<style>pre {{ line-height: 1.3; }} a {{ text-decoration: none; }} \
+         .dir {{ font-weight: bold; }}</style>"
+    )?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", title)?;
+    writeln!(out, "<pre>")?;
+    writeln!(
+        out,
+        "<a class=\"dir\" href=\"{}/\">{}</a>",
+        escape(base),
+        escape(root_label)
+    )?;
+    write_nodes(nodes, &[], root, base, out)?;
+    writeln!(out, "</pre>")?;
+    if let Some(stats) = stats {
+        writeln!(out, "<hr>")?;
+        writeln!(out, "<p>{}</p>", escape(&report_line(stats)))?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+fn write_nodes(
+    nodes: &[Node],
+    prefixes: &[bool],
+    root: Option<&Path>,
+    base: &str,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for (i, node) in nodes.iter().enumerate() {
+        let is_last = i == nodes.len() - 1;
+        let relative = root
+            .and_then(|root| node.path.strip_prefix(root).ok())
+            .unwrap_or(&node.path);
+        let is_dir = node.is_dir() || node.followed;
+
+        let mut href = format!("{}/{}", base, url_path(relative));
+        if is_dir {
+            href.push('/');
+        }
+        writeln!(
+            out,
+            "{}<a{} href=\"{}\">{}</a>",
+            branch_prefix(prefixes, is_last),
+            if is_dir { " class=\"dir\"" } else { "" },
+            escape(&href),
+            escape(&node.name)
+        )?;
+
+        let mut new_prefixes = prefixes.to_vec();
+        new_prefixes.push(is_last);
+        write_nodes(&node.children, &new_prefixes, root, base, out)?;
+    }
+    Ok(())
+}
+
+// Percent-encode each component of `path`, joined with `/`
+fn url_path(path: &Path) -> String {
+    let components: Vec<String> = path
+        .components()
+        .map(|component| {
+            let mut encoded = String::new();
+            for &byte in names::os_bytes(component.as_os_str()).iter() {
+                match byte {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                        encoded.push(byte as char)
+                    }
+                    _ => encoded.push_str(&format!("%{:02X}", byte)),
+                }
+            }
+            encoded
+        })
+        .collect();
+    components.join("/")
+}
@@ -0,0 +1,524 @@
+use clap::ValueEnum;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+// Conventions for numbers and dates, which can differ from the language
+static NUMERIC: OnceLock<Lang> = OnceLock::new();
+static TIME: OnceLock<Lang> = OnceLock::new();
+
+// Pick the language for the rest of the run: `lang` when given, else
+// the first of LC_ALL, LC_MESSAGES and LANG that is set. Numbers and
+// dates go by LC_NUMERIC and LC_TIME the same way, unless `env` is false,
+// which keeps them as in English.
+pub fn init(lang: Option<Lang>, env: bool) {
+    let _ = LANG.set(lang.unwrap_or_else(|| from_env("LC_MESSAGES")));
+    let _ = NUMERIC.set(if env {
+        from_env("LC_NUMERIC")
+    } else {
+        Lang::En
+    });
+    let _ = TIME.set(if env { from_env("LC_TIME") } else { Lang::En });
+}
+
+// The language of the first of LC_ALL, `category` and LANG that is set
+fn from_env(category: &str) -> Lang {
+    let locale = ["LC_ALL", category, "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+
+    // de_DE.UTF-8, fr_CA, es@euro...
+    match locale.split(['_', '.', '@']).next() {
+        Some("de") => Lang::De,
+        Some("fr") => Lang::Fr,
+        Some("es") => Lang::Es,
+        _ => Lang::En,
+    }
+}
+
+pub enum Msg {
+    Summary,
+    BelowDepthLimit,
+    NotSampled,
+    NotShown,
+    Rollup,
+    OutsideRoot,
+    Broken,
+    Unreadable,
+    Recursive,
+    VirtualFs,
+    AlreadyShown,
+    Mount,
+    MountOf,
+    Unowned,
+    Quarantined,
+    QuarantinedBy,
+    New,
+    Modified,
+    Filesystem,
+    TimeTaken,
+    UsedSummary,
+    Lines,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    FileLimit,
+    ErrorOpeningDir,
+    Errors,
+    OneError,
+    BrowseHelp,
+    InodeUsage,
+    Total,
+    OfInodes,
+    Oldest,
+    Newest,
+    Shape,
+    MaxDepth,
+    AverageDepth,
+    WidestDir,
+    LongestPath,
+    LongestName,
+    EntryCount,
+    CharCount,
+    ProblemNames,
+    NoProblemNames,
+    NotUtf8,
+    EdgeWhitespace,
+    Space,
+    ControlChar,
+    InvalidOnWindows,
+    ReservedOnWindows,
+    TrailingDot,
+    NameClashes,
+    NoNameClashes,
+    WouldCreate,
+    Exists,
+    Created,
+    Dir,
+    File,
+    SkippingSymlink,
+    OutsideDestination,
+    AddedFile,
+    RemovedFile,
+    ModifiedFile,
+    FilesWritten,
+    FilesChecked,
+    MalformedManifest,
+    // rtree diff
+    DiffSummary,
+}
+
+// The message in the current language, with `{}` placeholders
+pub fn tr(msg: Msg) -> &'static str {
+    translations(msg)[lang() as usize]
+}
+
+// The message in every language, for recognizing it whichever was used
+pub fn translations(msg: Msg) -> [&'static str; 4] {
+    // en, de, fr, es
+    match msg {
+        Msg::Summary => [
+            "{} directories, {} files",
+            "{} Verzeichnisse, {} Dateien",
+            "{} répertoires, {} fichiers",
+            "{} directorios, {} archivos",
+        ],
+        Msg::BelowDepthLimit => [
+            " (+ {} directories, {} files, {} below the depth limit)",
+            " (+ {} Verzeichnisse, {} Dateien, {} unterhalb der Tiefengrenze)",
+            " (+ {} répertoires, {} fichiers, {} sous la limite de profondeur)",
+            " (+ {} directorios, {} archivos, {} bajo el límite de profundidad)",
+        ],
+        Msg::NotSampled => [
+            " ({} entries not sampled)",
+            " ({} Einträge nicht ausgewählt)",
+            " ({} entrées non échantillonnées)",
+            " ({} entradas no muestreadas)",
+        ],
+        Msg::NotShown => [
+            "[{} entries not shown]",
+            "[{} Einträge nicht angezeigt]",
+            "[{} entrées non affichées]",
+            "[{} entradas no mostradas]",
+        ],
+        Msg::Rollup => [
+            "[+ {} files, {} dirs, {}]",
+            "[+ {} Dateien, {} Verz., {}]",
+            "[+ {} fichiers, {} rép., {}]",
+            "[+ {} archivos, {} dirs, {}]",
+        ],
+        Msg::OutsideRoot => [
+            "[outside root]",
+            "[außerhalb der Wurzel]",
+            "[hors de la racine]",
+            "[fuera de la raíz]",
+        ],
+        Msg::Broken => ["[broken]", "[defekt]", "[cassé]", "[roto]"],
+        Msg::Unreadable => ["unreadable", "unlesbar", "illisible", "ilegible"],
+        Msg::Recursive => [
+            "[recursive, not followed]",
+            "[rekursiv, nicht verfolgt]",
+            "[récursif, non suivi]",
+            "[recursivo, no seguido]",
+        ],
+        Msg::VirtualFs => [
+            "[virtual filesystem, skipped]",
+            "[virtuelles Dateisystem, übersprungen]",
+            "[système de fichiers virtuel, ignoré]",
+            "[sistema de archivos virtual, omitido]",
+        ],
+        Msg::AlreadyShown => [
+            "[already shown at {}]",
+            "[bereits gezeigt unter {}]",
+            "[déjà affiché sous {}]",
+            "[ya mostrado en {}]",
+        ],
+        Msg::Mount => [
+            "[mount]",
+            "[Einhängepunkt]",
+            "[point de montage]",
+            "[punto de montaje]",
+        ],
+        Msg::MountOf => [
+            "[mount: {}]",
+            "[Einhängepunkt: {}]",
+            "[point de montage : {}]",
+            "[punto de montaje: {}]",
+        ],
+        Msg::Unowned => [
+            "[unowned]",
+            "[ohne Besitzer]",
+            "[sans propriétaire]",
+            "[sin propietario]",
+        ],
+        Msg::Quarantined => [
+            "[quarantined]",
+            "[in Quarantäne]",
+            "[en quarantaine]",
+            "[en cuarentena]",
+        ],
+        Msg::QuarantinedBy => [
+            "[quarantined by {}]",
+            "[in Quarantäne durch {}]",
+            "[mis en quarantaine par {}]",
+            "[puesto en cuarentena por {}]",
+        ],
+        Msg::New => ["[new]", "[neu]", "[nouveau]", "[nuevo]"],
+        Msg::Modified => ["[modified]", "[geändert]", "[modifié]", "[modificado]"],
+        Msg::Filesystem => [
+            "Filesystem: {} total, {} used ({}%), {} free",
+            "Dateisystem: {} gesamt, {} belegt ({}%), {} frei",
+            "Système de fichiers : {} au total, {} utilisés ({} %), {} libres",
+            "Sistema de archivos: {} en total, {} usados ({}%), {} libres",
+        ],
+        Msg::TimeTaken => [
+            "Time taken: {}",
+            "Benötigte Zeit: {}",
+            "Temps écoulé : {}",
+            "Tiempo empleado: {}",
+        ],
+        Msg::UsedSummary => [
+            "{} used in {} directories, {} files",
+            "{} belegt in {} Verzeichnissen, {} Dateien",
+            "{} utilisés dans {} répertoires, {} fichiers",
+            "{} usados en {} directorios, {} archivos",
+        ],
+        Msg::Lines => ["{} lines", "{} Zeilen", "{} lignes", "{} líneas"],
+        Msg::Fifo => ["[fifo]", "[FIFO]", "[tube nommé]", "[fifo]"],
+        Msg::Socket => ["[socket]", "[Socket]", "[socket]", "[socket]"],
+        Msg::BlockDevice => [
+            "[block device]",
+            "[Blockgerät]",
+            "[périphérique bloc]",
+            "[dispositivo de bloques]",
+        ],
+        Msg::CharDevice => [
+            "[character device]",
+            "[Zeichengerät]",
+            "[périphérique caractère]",
+            "[dispositivo de caracteres]",
+        ],
+        Msg::FileLimit => [
+            "[{} entries exceeds limit, not opening]",
+            "[{} Einträge überschreiten die Grenze, nicht geöffnet]",
+            "[{} entrées dépassent la limite, non ouvert]",
+            "[{} entradas superan el límite, no abierto]",
+        ],
+        Msg::ErrorOpeningDir => [
+            "[error opening dir: {}]",
+            "[Fehler beim Öffnen des Verzeichnisses: {}]",
+            "[erreur à l'ouverture du répertoire : {}]",
+            "[error al abrir el directorio: {}]",
+        ],
+        Msg::Errors => ["{} errors", "{} Fehler", "{} erreurs", "{} errores"],
+        Msg::OneError => ["1 error", "1 Fehler", "1 erreur", "1 error"],
+        Msg::BrowseHelp => [
+            "↑↓ move  → open  ← close  q quit",
+            "↑↓ bewegen  → öffnen  ← schließen  q beenden",
+            "↑↓ déplacer  → ouvrir  ← fermer  q quitter",
+            "↑↓ mover  → abrir  ← cerrar  q salir",
+        ],
+        Msg::InodeUsage => [
+            "Inode usage:",
+            "Inode-Belegung:",
+            "Utilisation des inodes :",
+            "Uso de inodos:",
+        ],
+        Msg::Total => ["total", "gesamt", "total", "total"],
+        Msg::OfInodes => [
+            "(of {} inodes on the filesystem)",
+            "(von {} Inodes im Dateisystem)",
+            "(sur {} inodes du système de fichiers)",
+            "(de {} inodos en el sistema de archivos)",
+        ],
+        Msg::Oldest => ["Oldest:", "Älteste:", "Plus ancien :", "Más antiguo:"],
+        Msg::Newest => ["Newest:", "Neueste:", "Plus récent :", "Más reciente:"],
+        Msg::Shape => ["Shape:", "Form:", "Forme :", "Forma:"],
+        Msg::MaxDepth => [
+            "max depth",
+            "maximale Tiefe",
+            "profondeur max.",
+            "profundidad máx.",
+        ],
+        Msg::AverageDepth => [
+            "average depth",
+            "mittlere Tiefe",
+            "profondeur moyenne",
+            "profundidad media",
+        ],
+        Msg::WidestDir => [
+            "widest dir",
+            "breitestes Verz.",
+            "rép. le plus large",
+            "dir. más ancho",
+        ],
+        Msg::LongestPath => [
+            "longest path",
+            "längster Pfad",
+            "chemin le plus long",
+            "ruta más larga",
+        ],
+        Msg::LongestName => [
+            "longest name",
+            "längster Name",
+            "nom le plus long",
+            "nombre más largo",
+        ],
+        Msg::EntryCount => [
+            "({} entries)",
+            "({} Einträge)",
+            "({} entrées)",
+            "({} entradas)",
+        ],
+        Msg::CharCount => [
+            "({} chars)",
+            "({} Zeichen)",
+            "({} caractères)",
+            "({} caracteres)",
+        ],
+        Msg::ProblemNames => [
+            "Problematic names:",
+            "Problematische Namen:",
+            "Noms problématiques :",
+            "Nombres problemáticos:",
+        ],
+        Msg::NoProblemNames => [
+            "No problematic names found",
+            "Keine problematischen Namen gefunden",
+            "Aucun nom problématique trouvé",
+            "No se encontraron nombres problemáticos",
+        ],
+        Msg::NotUtf8 => [
+            "not valid UTF-8",
+            "kein gültiges UTF-8",
+            "UTF-8 invalide",
+            "UTF-8 no válido",
+        ],
+        Msg::EdgeWhitespace => [
+            "leading/trailing whitespace",
+            "Leerraum am Anfang/Ende",
+            "espace au début/à la fin",
+            "espacio al inicio/final",
+        ],
+        Msg::Space => ["space", "Leerzeichen", "espace", "espacio"],
+        Msg::ControlChar => [
+            "control character",
+            "Steuerzeichen",
+            "caractère de contrôle",
+            "carácter de control",
+        ],
+        Msg::InvalidOnWindows => [
+            "invalid on Windows: {}",
+            "unter Windows ungültig: {}",
+            "invalide sous Windows : {}",
+            "no válido en Windows: {}",
+        ],
+        Msg::ReservedOnWindows => [
+            "reserved on Windows",
+            "unter Windows reserviert",
+            "réservé sous Windows",
+            "reservado en Windows",
+        ],
+        Msg::TrailingDot => [
+            "trailing dot",
+            "Punkt am Ende",
+            "point final",
+            "punto final",
+        ],
+        Msg::NameClashes => [
+            "Name clashes:",
+            "Namenskonflikte:",
+            "Conflits de noms :",
+            "Conflictos de nombres:",
+        ],
+        Msg::NoNameClashes => [
+            "No name clashes found",
+            "Keine Namenskonflikte gefunden",
+            "Aucun conflit de noms trouvé",
+            "No se encontraron conflictos de nombres",
+        ],
+        Msg::WouldCreate => ["would create", "würde anlegen", "créerait", "se crearía"],
+        Msg::Exists => ["exists", "existiert", "existe", "existe"],
+        Msg::Created => ["created", "angelegt", "créé", "creado"],
+        Msg::Dir => ["dir", "Verz.", "rép.", "dir"],
+        Msg::File => ["file", "Datei", "fichier", "archivo"],
+        Msg::SkippingSymlink => [
+            "line {}: skipping symlink '{}'",
+            "Zeile {}: symbolischer Link '{}' übersprungen",
+            "ligne {} : lien symbolique '{}' ignoré",
+            "línea {}: se omite el enlace simbólico '{}'",
+        ],
+        Msg::OutsideDestination => [
+            "line {}: '{}' points outside of the destination",
+            "Zeile {}: '{}' zeigt aus dem Ziel heraus",
+            "ligne {} : '{}' pointe hors de la destination",
+            "línea {}: '{}' apunta fuera del destino",
+        ],
+        Msg::AddedFile => ["added", "hinzugefügt", "ajouté", "añadido"],
+        Msg::RemovedFile => ["removed", "entfernt", "supprimé", "eliminado"],
+        Msg::ModifiedFile => ["modified", "geändert", "modifié", "modificado"],
+        Msg::FilesWritten => [
+            "{} files written to {}",
+            "{} Dateien in {} geschrieben",
+            "{} fichiers écrits dans {}",
+            "{} archivos escritos en {}",
+        ],
+        Msg::FilesChecked => [
+            "{} files checked, {} added, {} removed, {} modified",
+            "{} Dateien geprüft, {} hinzugefügt, {} entfernt, {} geändert",
+            "{} fichiers vérifiés, {} ajoutés, {} supprimés, {} modifiés",
+            "{} archivos comprobados, {} añadidos, {} eliminados, {} modificados",
+        ],
+        Msg::MalformedManifest => [
+            "{}:{}: malformed manifest line",
+            "{}:{}: fehlerhafte Zeile im Manifest",
+            "{}:{} : ligne de manifeste mal formée",
+            "{}:{}: línea de manifiesto mal formada",
+        ],
+        Msg::DiffSummary => [
+            "{} added, {} removed, {} modified",
+            "{} hinzugefügt, {} entfernt, {} geändert",
+            "{} ajoutés, {} supprimés, {} modifiés",
+            "{} añadidos, {} eliminados, {} modificados",
+        ],
+    }
+}
+
+fn lang() -> Lang {
+    *LANG.get_or_init(|| from_env("LC_MESSAGES"))
+}
+
+fn numeric() -> Lang {
+    *NUMERIC.get_or_init(|| from_env("LC_NUMERIC"))
+}
+
+fn time() -> Lang {
+    *TIME.get_or_init(|| from_env("LC_TIME"))
+}
+
+// Separator between the whole and fractional part of numbers
+pub fn decimal_point() -> char {
+    match numeric() {
+        Lang::En => '.',
+        Lang::De | Lang::Fr | Lang::Es => ',',
+    }
+}
+
+// Separator between groups of thousands
+pub fn thousands_separator() -> &'static str {
+    match numeric() {
+        Lang::En => ",",
+        Lang::De | Lang::Es => ".",
+        // Narrow no-break space
+        Lang::Fr => "\u{202f}",
+    }
+}
+
+// chrono format for dates with times
+pub fn date_format() -> &'static str {
+    match time() {
+        Lang::En => "%Y-%m-%d %H:%M:%S",
+        Lang::De => "%d.%m.%Y %H:%M:%S",
+        Lang::Fr | Lang::Es => "%d/%m/%Y %H:%M:%S",
+    }
+}
+
+// Abbreviated name of `month` (1 for January), padded like ls does to
+// the longest of the year so the dates after it line up
+pub fn month_abbr(month: u32) -> String {
+    let names = match time() {
+        Lang::En => [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+        Lang::De => [
+            "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+        ],
+        Lang::Fr => [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+        Lang::Es => [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+        ],
+    };
+    let width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let index = (month as usize).clamp(1, 12) - 1;
+    format!("{:<width$}", names[index])
+}
+
+// The messages padded to the same width, for labels that line up in
+// every language
+pub fn padded<const N: usize>(msgs: [Msg; N]) -> [String; N] {
+    let texts = msgs.map(tr);
+    let width = texts.iter().map(|t| t.chars().count()).max().unwrap_or(0);
+    texts.map(|text| format!("{:<width$}", text))
+}
+
+// The message with its placeholders filled in order
+pub fn fill(msg: Msg, args: &[&dyn Display]) -> String {
+    let mut parts = tr(msg).split("{}");
+    let mut text = parts.next().unwrap_or("").to_string();
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+    text
+}
@@ -0,0 +1,178 @@
+use clap::ValueEnum;
+use std::env;
+use std::fs;
+
+use crate::platform;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum IconSet {
+    /// Nerd Font glyphs, unless the terminal is unlikely to have them
+    Auto,
+    /// Nerd Font glyphs (needs a patched font)
+    Nerd,
+    /// Plain Unicode symbols, for terminals without a Nerd Font
+    Unicode,
+}
+
+impl IconSet {
+    // The Linux console and dumb terminals can't draw Nerd Font glyphs
+    pub fn resolve(self) -> IconSet {
+        match self {
+            IconSet::Auto => match env::var("TERM").as_deref() {
+                Ok("linux") | Ok("dumb") => IconSet::Unicode,
+                _ => IconSet::Nerd,
+            },
+            set => set,
+        }
+    }
+}
+
+enum Kind {
+    Directory,
+    GitDirectory,
+    Symlink,
+    Executable,
+    File,
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+    C,
+    Cpp,
+    Java,
+    Ruby,
+    Shell,
+    Html,
+    Css,
+    Markdown,
+    Json,
+    Config,
+    Lock,
+    Image,
+    Audio,
+    Video,
+    Archive,
+    Pdf,
+    Text,
+}
+
+// Icon for the entry followed by a space
+pub fn icon(set: IconSet, name: &str, metadata: &fs::Metadata) -> &'static str {
+    let kind = kind(name, metadata);
+    match set {
+        IconSet::Unicode => unicode(kind),
+        IconSet::Nerd | IconSet::Auto => nerd(kind),
+    }
+}
+
+fn kind(name: &str, metadata: &fs::Metadata) -> Kind {
+    if metadata.file_type().is_symlink() {
+        return Kind::Symlink;
+    }
+    if metadata.is_dir() {
+        return match name {
+            ".git" => Kind::GitDirectory,
+            _ => Kind::Directory,
+        };
+    }
+
+    match name {
+        "Cargo.lock" | "package-lock.json" | "yarn.lock" | "poetry.lock" | "go.sum" => {
+            return Kind::Lock
+        }
+        "Makefile" | "Dockerfile" | ".gitignore" | ".editorconfig" => return Kind::Config,
+        _ => {}
+    }
+    let ext = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+        _ => String::new(),
+    };
+    match ext.as_str() {
+        "rs" => Kind::Rust,
+        "py" | "pyi" => Kind::Python,
+        "js" | "mjs" | "cjs" | "jsx" => Kind::JavaScript,
+        "ts" | "tsx" => Kind::TypeScript,
+        "go" => Kind::Go,
+        "c" | "h" => Kind::C,
+        "cpp" | "cc" | "cxx" | "hpp" => Kind::Cpp,
+        "java" | "jar" => Kind::Java,
+        "rb" => Kind::Ruby,
+        "sh" | "bash" | "zsh" | "fish" => Kind::Shell,
+        "html" | "htm" => Kind::Html,
+        "css" | "scss" | "sass" => Kind::Css,
+        "md" | "markdown" => Kind::Markdown,
+        "json" => Kind::Json,
+        "toml" | "yaml" | "yml" | "ini" | "conf" | "cfg" => Kind::Config,
+        "lock" => Kind::Lock,
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "bmp" | "ico" => Kind::Image,
+        "mp3" | "flac" | "wav" | "ogg" | "m4a" => Kind::Audio,
+        "mp4" | "mkv" | "webm" | "mov" | "avi" => Kind::Video,
+        "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "zst" | "7z" | "rar" => Kind::Archive,
+        "pdf" => Kind::Pdf,
+        "txt" | "log" => Kind::Text,
+        _ if platform::is_executable(name, metadata) => Kind::Executable,
+        _ => Kind::File,
+    }
+}
+
+fn nerd(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Directory => "\u{f07b} ",
+        Kind::GitDirectory => "\u{e5fb} ",
+        Kind::Symlink => "\u{f0c1} ",
+        Kind::Executable => "\u{f489} ",
+        Kind::File => "\u{f15b} ",
+        Kind::Rust => "\u{e7a8} ",
+        Kind::Python => "\u{e606} ",
+        Kind::JavaScript => "\u{e74e} ",
+        Kind::TypeScript => "\u{e628} ",
+        Kind::Go => "\u{e626} ",
+        Kind::C => "\u{e61e} ",
+        Kind::Cpp => "\u{e61d} ",
+        Kind::Java => "\u{e738} ",
+        Kind::Ruby => "\u{e739} ",
+        Kind::Shell => "\u{f489} ",
+        Kind::Html => "\u{e736} ",
+        Kind::Css => "\u{e749} ",
+        Kind::Markdown => "\u{e73e} ",
+        Kind::Json => "\u{e60b} ",
+        Kind::Config => "\u{e615} ",
+        Kind::Lock => "\u{f023} ",
+        Kind::Image => "\u{f1c5} ",
+        Kind::Audio => "\u{f1c7} ",
+        Kind::Video => "\u{f1c8} ",
+        Kind::Archive => "\u{f410} ",
+        Kind::Pdf => "\u{f1c1} ",
+        Kind::Text => "\u{f15c} ",
+    }
+}
+
+// Fewer distinct symbols: most languages are just source files here
+fn unicode(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Directory | Kind::GitDirectory => "📁 ",
+        Kind::Symlink => "🔗 ",
+        Kind::Executable | Kind::Shell => "⚙️ ",
+        Kind::Rust => "🦀 ",
+        Kind::Python => "🐍 ",
+        Kind::Config | Kind::Json => "🔧 ",
+        Kind::Lock => "🔒 ",
+        Kind::Image => "🖼️ ",
+        Kind::Audio => "🎵 ",
+        Kind::Video => "🎬 ",
+        Kind::Archive => "📦 ",
+        Kind::Pdf => "📕 ",
+        Kind::Markdown | Kind::Text => "📝 ",
+        Kind::File
+        | Kind::JavaScript
+        | Kind::TypeScript
+        | Kind::Go
+        | Kind::C
+        | Kind::Cpp
+        | Kind::Java
+        | Kind::Ruby
+        | Kind::Html
+        | Kind::Css => "📄 ",
+    }
+}
@@ -0,0 +1,137 @@
+use ignore::gitignore::{self, Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+// Ignore files read in each directory, later ones taking precedence:
+// .ignore (as used by ripgrep and friends) overrides .gitignore, and
+// .rtreeignore overrides both
+const GIT_FILES: [&str; 1] = [".gitignore"];
+const OTHER_FILES: [&str; 2] = [".ignore", ".rtreeignore"];
+
+// Rules from the ignore files that apply to the walk
+pub struct Ignores {
+    root: PathBuf,
+    // Whether the git ones count (not with --no-gitignore)
+    git: bool,
+    // The rules of each directory seen so far, None when it has no
+    // ignore files. Read on first use, as the walk reaches the directory.
+    dirs: RwLock<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+    // Absolute form of `root`, for matching against `excludes`
+    canonical_root: Option<PathBuf>,
+    // .git/info/exclude and then the user's core.excludesFile, for a
+    // root inside a repository. They give way to any .gitignore.
+    excludes: Vec<Gitignore>,
+}
+
+impl Ignores {
+    // Ignore files at `root` and below, read with the full gitignore
+    // syntax: negation, `**`, directory-only patterns and anchoring.
+    // Without `git` only .ignore and .rtreeignore are read.
+    pub fn load(root: &Path, git: bool) -> Ignores {
+        let canonical_root = fs::canonicalize(root).ok();
+        let repo = canonical_root
+            .as_deref()
+            .filter(|_| git)
+            .and_then(|root| root.ancestors().find(|dir| dir.join(".git").exists()));
+        let excludes = match repo {
+            Some(repo) => [
+                Some(repo.join(".git").join("info").join("exclude")),
+                gitignore::gitconfig_excludes_path(),
+            ]
+            .into_iter()
+            .flatten()
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                let mut builder = GitignoreBuilder::new(repo);
+                builder.add(path);
+                builder.build().ok()
+            })
+            .collect(),
+            None => Vec::new(),
+        };
+
+        Ignores {
+            root: root.to_path_buf(),
+            git,
+            dirs: RwLock::default(),
+            canonical_root,
+            excludes,
+        }
+    }
+
+    // Whether `path`, an entry of a directory being listed, is ignored.
+    // Like git, the ignore files closest to the entry decide, with each
+    // file's patterns relative to its own directory.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for dir in path.ancestors().skip(1) {
+            if let Some(rules) = self.rules(dir) {
+                let matched = rules.matched(path, is_dir);
+                if matched.is_ignore() {
+                    return true;
+                }
+                if matched.is_whitelist() {
+                    return false;
+                }
+            }
+            if dir == self.root {
+                break;
+            }
+        }
+
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        if let Some(absolute) = self.canonical_root.as_ref().map(|r| r.join(relative)) {
+            for exclude in &self.excludes {
+                let matched = exclude.matched(&absolute, is_dir);
+                if matched.is_ignore() {
+                    return true;
+                }
+                if matched.is_whitelist() {
+                    return false;
+                }
+            }
+        }
+        false
+    }
+
+    // The rules of `dir`, from the cache when it's been read before. The
+    // threads of a parallel walk only wait on each other to add a
+    // directory, not to look one up.
+    fn rules(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        if let Some(rules) = self.dirs.read().unwrap().get(dir) {
+            return rules.clone();
+        }
+        // Read before locking; two threads reading the same files at
+        // once end up with the same rules
+        let rules = read(dir, self.git).map(Arc::new);
+        self.dirs
+            .write()
+            .unwrap()
+            .entry(dir.to_path_buf())
+            .or_insert(rules)
+            .clone()
+    }
+}
+
+// The rules of the ignore files in `dir` as one matcher, where the last
+// matching line wins. Lines that don't parse are skipped, the rest still
+// apply.
+fn read(dir: &Path, git: bool) -> Option<Gitignore> {
+    let git_files = if git { &GIT_FILES[..] } else { &[] };
+    let paths: Vec<PathBuf> = git_files
+        .iter()
+        .chain(&OTHER_FILES)
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .collect();
+    if paths.is_empty() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    for path in paths {
+        builder.add(path);
+    }
+    builder.build().ok()
+}
@@ -0,0 +1,96 @@
+use globset::{Glob, GlobMatcher};
+use std::fs;
+use std::path::Path;
+
+// Files searched for annotations, in order of preference
+const INFO_FILES: [&str; 2] = [".rtreeinfo", ".info"];
+
+pub struct InfoRule {
+    patterns: Vec<(GlobMatcher, bool)>,
+    comment: String,
+}
+
+// Load annotations from the first info file found in `root`.
+//
+// The format is the one GNU tree uses for `.info` files: one or more
+// pattern lines followed by tab-indented comment lines. A single line of
+// the form `pattern<TAB>comment` is accepted as a shorthand.
+pub fn load(root: &Path) -> Vec<InfoRule> {
+    let text = match INFO_FILES
+        .iter()
+        .find_map(|name| fs::read_to_string(root.join(name)).ok())
+    {
+        Some(text) => text,
+        None => return Vec::new(),
+    };
+
+    let mut rules: Vec<InfoRule> = Vec::new();
+    let mut pending: Vec<(GlobMatcher, bool)> = Vec::new();
+
+    for line in text.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('\t') {
+            let comment = comment.trim();
+            if !pending.is_empty() {
+                rules.push(InfoRule {
+                    patterns: std::mem::take(&mut pending),
+                    comment: comment.to_string(),
+                });
+            } else if let Some(last) = rules.last_mut() {
+                // Continuation of a multi-line comment
+                last.comment.push(' ');
+                last.comment.push_str(comment);
+            }
+            continue;
+        }
+
+        match line.split_once('\t') {
+            Some((pattern, comment)) => {
+                pending.extend(parse_pattern(pattern));
+                rules.push(InfoRule {
+                    patterns: std::mem::take(&mut pending),
+                    comment: comment.trim().to_string(),
+                });
+            }
+            None => pending.extend(parse_pattern(line)),
+        }
+    }
+
+    rules
+}
+
+// A trailing '/' restricts the pattern to directories
+fn parse_pattern(pattern: &str) -> Option<(GlobMatcher, bool)> {
+    let pattern = pattern.trim();
+    let dir_only = pattern.len() > 1 && pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/').trim_start_matches("./");
+    Glob::new(pattern)
+        .ok()
+        .map(|glob| (glob.compile_matcher(), dir_only))
+}
+
+// Find the comment for an entry given its path relative to the root.
+// Patterns containing a '/' match the whole relative path, others the
+// file name alone.
+pub fn lookup<'a>(rules: &'a [InfoRule], relative: &Path, is_dir: bool) -> Option<&'a str> {
+    let file_name = relative.file_name()?;
+
+    rules
+        .iter()
+        .find(|rule| {
+            rule.patterns.iter().any(|(pattern, dir_only)| {
+                if *dir_only && !is_dir {
+                    return false;
+                }
+                if pattern.glob().glob().contains('/') {
+                    pattern.is_match(relative)
+                } else {
+                    pattern.is_match(file_name)
+                }
+            })
+        })
+        .map(|rule| rule.comment.as_str())
+}
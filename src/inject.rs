@@ -0,0 +1,135 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{names, render_tree, walk_root, Opt};
+
+const START_MARKER: &str = "<!-- rtree:start -->";
+const END_MARKER: &str = "<!-- rtree:end -->";
+
+// Replace every marked snippet in `file` with a freshly rendered tree.
+// Returns false when `check` is set and the file is out of date.
+pub fn inject(file: &Path, dir: Option<&Path>, check: bool, opt: &Opt) -> io::Result<bool> {
+    let text = fs::read_to_string(file)?;
+
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => match file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        },
+    };
+    let snippet = render_snippet(&dir, opt)?;
+
+    let updated = replace_snippets(&text, &snippet).map_err(|msg| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", file.display(), msg),
+        )
+    })?;
+
+    if updated == text {
+        return Ok(true);
+    }
+    if check {
+        eprintln!("rtree: {} is out of date", file.display());
+        return Ok(false);
+    }
+
+    fs::write(file, updated)?;
+    Ok(true)
+}
+
+// Render the tree as a fenced Markdown code block
+fn render_snippet(dir: &Path, opt: &Opt) -> io::Result<String> {
+    // Escape codes have no place in a README
+    colored::control::set_override(false);
+
+    let root = fs::canonicalize(dir)?;
+    let label = match root.file_name() {
+        Some(name) => names::display(name, opt.escape),
+        None => names::display(root.as_os_str(), opt.escape),
+    };
+
+    let mut tree = Vec::new();
+    render_tree(
+        &root,
+        &label,
+        opt,
+        &mut |path| walk_root(path, opt),
+        &mut tree,
+    )?;
+
+    Ok(format!("```text\n{}```\n", String::from_utf8_lossy(&tree)))
+}
+
+fn replace_snippets(text: &str, snippet: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len() + snippet.len());
+    let mut rest = text;
+    let mut found = false;
+
+    while let Some(start) = rest.find(START_MARKER) {
+        let body_start = start + START_MARKER.len();
+        let end = match rest[body_start..].find(END_MARKER) {
+            Some(end) => body_start + end,
+            None => {
+                return Err(format!(
+                    "{} without a matching {}",
+                    START_MARKER, END_MARKER
+                ))
+            }
+        };
+
+        result.push_str(&rest[..body_start]);
+        result.push('\n');
+        result.push_str(snippet);
+        result.push_str(END_MARKER);
+        rest = &rest[end + END_MARKER.len()..];
+        found = true;
+    }
+
+    if !found {
+        return Err(format!("no {} marker found", START_MARKER));
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNIPPET: &str = "```text\nsrc\n```\n";
+
+    #[test]
+    fn replaces_the_marked_snippet() {
+        let text = "# Project\n<!-- rtree:start -->\nold tree\n<!-- rtree:end -->\nMore\n";
+        assert_eq!(
+            replace_snippets(text, SNIPPET).unwrap(),
+            "# Project\n<!-- rtree:start -->\n```text\nsrc\n```\n<!-- rtree:end -->\nMore\n"
+        );
+    }
+
+    #[test]
+    fn replaces_every_snippet() {
+        let text =
+            "<!-- rtree:start --><!-- rtree:end -->\nand\n<!-- rtree:start -->x<!-- rtree:end -->";
+        let updated = replace_snippets(text, SNIPPET).unwrap();
+        assert_eq!(updated.matches(SNIPPET).count(), 2);
+        assert!(!updated.contains(">x<"));
+    }
+
+    #[test]
+    fn is_stable() {
+        let text = "<!-- rtree:start -->\nold\n<!-- rtree:end -->\n";
+        let once = replace_snippets(text, SNIPPET).unwrap();
+        assert_eq!(replace_snippets(&once, SNIPPET).unwrap(), once);
+    }
+
+    #[test]
+    fn rejects_missing_markers() {
+        assert!(replace_snippets("no markers here", SNIPPET).is_err());
+        assert!(replace_snippets("<!-- rtree:start -->\nunfinished", SNIPPET).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use std::io::{self, Write};
+
+use crate::walk::Node;
+use crate::{columns, names, Stats};
+
+// Write the tree as a JSON array: the root directory with everything
+// below it nested in "children", then the report. The layout follows
+// GNU tree -J, except that it calls the nested entries "contents".
+pub fn write_tree(
+    root_label: &str,
+    root_path: Option<&str>,
+    nodes: &[Node],
+    stats: &Stats,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "[")?;
+    write!(
+        out,
+        "  {{\"type\":\"directory\",\"name\":{}",
+        string(root_label)
+    )?;
+    if let Some(path) = root_path {
+        write!(out, ",\"path\":{}", string(path))?;
+    }
+    write_children(nodes, 2, out)?;
+    writeln!(out, "}},")?;
+    write!(
+        out,
+        "  {{\"type\":\"report\",\"directories\":{},\"files\":{},\"errors\":{}",
+        stats.directories, stats.files, stats.errors
+    )?;
+    if let Some(used) = stats.used {
+        write!(out, ",\"size\":{}", used)?;
+    }
+    writeln!(out, "}}")?;
+    writeln!(out, "]")
+}
+
+fn write_children(nodes: &[Node], indent: usize, out: &mut dyn Write) -> io::Result<()> {
+    write!(out, ",\"children\":[")?;
+    for (i, node) in nodes.iter().enumerate() {
+        write!(out, "\n{:indent$}", "", indent = indent + 2)?;
+        write_node(node, indent + 2, out)?;
+        if i + 1 < nodes.len() {
+            write!(out, ",")?;
+        }
+    }
+    if !nodes.is_empty() {
+        write!(out, "\n{:indent$}", "", indent = indent)?;
+    }
+    write!(out, "]")
+}
+
+fn write_node(node: &Node, indent: usize, out: &mut dyn Write) -> io::Result<()> {
+    let kind = kind(node);
+    write!(
+        out,
+        "{{\"type\":\"{}\",\"name\":{},\"path\":{}",
+        kind,
+        string(&node.name),
+        string(&names::display(node.path.as_os_str(), false))
+    )?;
+    if kind == "link" {
+        if let Ok(target) = fs::read_link(&node.path) {
+            write!(
+                out,
+                ",\"target\":{}",
+                string(&names::display(target.as_os_str(), false))
+            )?;
+        }
+    }
+    if let Some(du) = node.du {
+        write!(out, ",\"size\":{}", du)?;
+    }
+    // GNU tree has an "error" in place of the contents
+    if let Some(error) = &node.error {
+        write!(out, ",\"error\":{}", string(error))?;
+    }
+    if kind == "directory" || node.followed {
+        write_children(&node.children, indent, out)?;
+    }
+    write!(out, "}}")
+}
+
+// The entry types GNU tree uses, shared with the XML element names
+pub fn kind(node: &Node) -> &'static str {
+    match columns::type_char(node.metadata.file_type()) {
+        'l' => "link",
+        'd' => "directory",
+        'p' => "fifo",
+        's' => "socket",
+        'b' => "block",
+        'c' => "char",
+        _ => "file",
+    }
+}
+
+// `s` as a quoted JSON string
+pub fn string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,1903 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use colored::*;
+use globset::{GlobBuilder, GlobMatcher};
+use i18n::Msg;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+mod badges;
+mod browse;
+mod builder;
+mod checksum;
+mod clipboard;
+mod codeowners;
+mod columns;
+mod config;
+mod diff;
+mod dot;
+mod errors;
+mod filetype;
+mod fsinfo;
+mod git;
+mod glyphs;
+mod html;
+mod i18n;
+mod icons;
+mod ignores;
+mod info;
+mod inject;
+mod json;
+mod lscolors;
+mod macattrs;
+mod manifest;
+mod markdown;
+mod names;
+mod platform;
+mod query;
+mod reports;
+mod scaffold;
+mod sort;
+mod streams;
+mod table;
+mod theme;
+mod units;
+mod walk;
+mod watch;
+mod xml;
+
+pub use builder::{Entries, Tree, TreeBuilder};
+pub use errors::TraversalError;
+pub use sort::SortKey;
+pub use walk::{Node, Rollup};
+
+#[derive(Parser)]
+#[command(
+    name = "rtree",
+    version,
+    author = "Raunak Raj <bajrangcoders@gmail.com>",
+    about = "Tree clone",
+    disable_help_flag = true,
+    // Lets flags given on the command line override those of a preset
+    args_override_self = true,
+    // `rtree src manifest create` shouldn't take `manifest` for a path
+    subcommand_precedence_over_arg = true
+)]
+struct Opt {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Paths where to run rtree
+    paths: Vec<PathBuf>,
+
+    /// Show all paths as children of one root called LABEL
+    #[arg(long, value_name = "LABEL")]
+    merge_roots: Option<String>,
+
+    /// Language of messages (defaults to LC_ALL, LC_MESSAGES or LANG)
+    #[arg(long, value_enum)]
+    lang: Option<i18n::Lang>,
+
+    /// Add the flags of a preset from the config file's [presets] table
+    #[arg(long, value_name = "NAME")]
+    preset: Vec<String>,
+
+    /// Ignore config files and locale settings from the environment
+    #[arg(long)]
+    no_config: bool,
+
+    /// Descend at most N levels below the root
+    #[arg(short = 'L', long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Don't descend into directories with more than N entries
+    #[arg(long, value_name = "N")]
+    filelimit: Option<usize>,
+
+    /// List directories only
+    #[arg(short = 'd', long)]
+    dirs_only: bool,
+
+    /// Leave out directories that are empty once filtered
+    #[arg(long)]
+    prune: bool,
+
+    /// Only show entries at least N levels deep (and the directories leading there)
+    #[arg(long, value_name = "N")]
+    min_depth: Option<usize>,
+
+    /// Summarize what --max-depth leaves out on the deepest directories
+    #[arg(long)]
+    rollup: bool,
+
+    /// Print the tree as JSON
+    #[arg(short = 'J', long)]
+    json: bool,
+
+    /// Print the tree as XML, in the layout of GNU tree -X
+    #[arg(short = 'X', long, conflicts_with = "json")]
+    xml: bool,
+
+    /// Write an HTML page linking every entry below BASEHREF
+    #[arg(short = 'H', long = "html", value_name = "BASEHREF", conflicts_with_all = ["json", "xml"])]
+    html: Option<String>,
+
+    /// Title of the HTML page
+    #[arg(short = 'T', long, default_value = "Directory Tree")]
+    title: String,
+
+    /// Print the tree as Markdown, a bullet list unless --markdown=code
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "list",
+        conflicts_with_all = ["json", "xml", "html"]
+    )]
+    markdown: Option<markdown::MarkdownStyle>,
+
+    /// Print one comma-separated row per entry (path, depth, type, size, mtime)
+    #[arg(long, conflicts_with_all = ["json", "xml", "html", "markdown"])]
+    csv: bool,
+
+    /// Like --csv, separated by tabs
+    #[arg(long, conflicts_with_all = ["json", "xml", "html", "markdown", "csv"])]
+    tsv: bool,
+
+    /// Print the tree as a Graphviz digraph, for `dot -Tpng`
+    #[arg(long, conflicts_with_all = ["json", "xml", "html", "markdown", "csv", "tsv"])]
+    dot: bool,
+
+    /// Print every entry as a plain path, without branch glyphs or report
+    #[arg(long, conflicts_with_all = ["json", "xml", "html", "markdown", "csv", "tsv", "dot"])]
+    flat: bool,
+
+    /// End each path of --flat with a NUL byte instead of a newline (implies --flat)
+    #[arg(long, conflicts_with_all = ["json", "xml", "html", "markdown", "csv", "tsv", "dot"])]
+    print0: bool,
+
+    /// Characters to draw the branches with
+    #[arg(long, value_enum, default_value_t = glyphs::Charset::Utf8)]
+    charset: glyphs::Charset,
+
+    /// Glyph in front of entries followed by others (default "├── ")
+    #[arg(long, value_name = "STR")]
+    branch: Option<String>,
+
+    /// Glyph in front of the last entry of a directory (default "└── ")
+    #[arg(long, value_name = "STR")]
+    last_branch: Option<String>,
+
+    /// Glyph continuing a branch past deeper entries (default "│   ")
+    #[arg(long, value_name = "STR")]
+    pipe: Option<String>,
+
+    /// Columns per level, padding or trimming the glyphs to fit
+    #[arg(long, value_name = "N")]
+    indent: Option<usize>,
+
+    /// Colors from a TOML theme file instead of the built-in ones
+    #[arg(long, value_name = "FILE")]
+    theme: Option<PathBuf>,
+
+    /// Put an icon by file type in front of each name
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SET",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto"
+    )]
+    icons: Option<icons::IconSet>,
+
+    /// Leave out the "N directories, M files" report
+    #[arg(long)]
+    noreport: bool,
+
+    /// Browse the tree in the terminal, opening directories with the arrow keys
+    #[arg(long, conflicts_with_all = ["json", "xml", "html", "markdown", "csv", "tsv", "dot", "flat", "print0", "output", "copy"])]
+    interactive: bool,
+
+    /// Redraw the tree as files change, highlighting what changed
+    #[arg(long, conflicts_with_all = ["interactive", "output", "copy"])]
+    watch: bool,
+
+    /// Print how long the listing took
+    #[arg(long)]
+    timing: bool,
+
+    /// When to use colors
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Write the tree to FILE, without colors (unless --color=always)
+    #[arg(short = 'o', long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Show the inode number of each entry
+    #[arg(long)]
+    inodes: bool,
+
+    /// Show the device number of each entry
+    #[arg(long)]
+    device: bool,
+
+    /// Show the type and permissions of each entry, like `drwxr-xr-x`
+    #[arg(short = 'p', long)]
+    permissions: bool,
+
+    /// Show how many hard links each entry has
+    #[arg(long)]
+    nlink: bool,
+
+    /// Show the owner of each entry (the uid when it has no name)
+    #[arg(short = 'u', long)]
+    owner: bool,
+
+    /// Show the group of each entry (the gid when it has no name)
+    #[arg(short = 'g', long)]
+    group: bool,
+
+    /// Show the size of each entry in bytes
+    #[arg(short = 's', long)]
+    size: bool,
+
+    /// Show sizes in KiB, MiB, GiB... (implies -s)
+    #[arg(long)]
+    human_readable: bool,
+
+    /// Show the size of directories as the total of everything in them (implies -s)
+    #[arg(long)]
+    du: bool,
+
+    /// Show when each entry was last modified
+    #[arg(short = 'D', long)]
+    date: bool,
+
+    /// strftime format for -D, e.g. "%Y-%m-%d %H:%M" (implies -D)
+    #[arg(long, value_name = "FORMAT", value_parser = units::parse_time_format)]
+    timefmt: Option<String>,
+
+    /// Mark directories with /, executables with *, links with @, FIFOs with | and sockets with =
+    #[arg(short = 'F', long)]
+    classify: bool,
+
+    /// Count the lines of text files, with totals for directories and the tree
+    #[arg(long)]
+    lines: bool,
+
+    /// Show a digest of each file, computed in parallel
+    #[arg(long, value_enum, value_name = "ALGO")]
+    checksum: Option<checksum::Algorithm>,
+
+    /// Show the MIME type of each file, judged by its contents
+    #[arg(long)]
+    mime: bool,
+
+    /// Show the path of each entry in the tree instead of its name
+    #[arg(short = 'f', long)]
+    full_path: bool,
+
+    /// Escape control characters and backslashes in names, as \n, \t, \\ and the like
+    #[arg(short = 'b', long)]
+    escape: bool,
+
+    /// List entries level by level as paths instead of drawing a tree
+    #[arg(long)]
+    bfs: bool,
+
+    /// Show at most N entries per directory
+    #[arg(long, value_name = "N")]
+    sample: Option<usize>,
+
+    /// Which entries --sample keeps
+    #[arg(long, value_enum, default_value_t = SampleMode::First)]
+    sample_mode: SampleMode,
+
+    /// Order of the entries in each directory
+    #[arg(long, value_enum, value_name = "KEY", default_value_t = sort::SortKey::Name)]
+    sort: sort::SortKey,
+
+    /// Sort by last modification time, newest first (--sort=mtime)
+    #[arg(short = 't')]
+    sort_mtime: bool,
+
+    /// Sort by name with numbers in order, file2 before file10 (--sort=version)
+    #[arg(short = 'v', conflicts_with = "sort_mtime")]
+    sort_version: bool,
+
+    /// Don't sort, listing entries in directory order (--sort=none); the fastest
+    #[arg(short = 'U', long)]
+    unsorted: bool,
+
+    /// Reverse the order of the sort
+    #[arg(short = 'r', long)]
+    reverse: bool,
+
+    /// List directories before files
+    #[arg(long)]
+    dirsfirst: bool,
+
+    /// List files before directories
+    #[arg(long, conflicts_with = "dirsfirst")]
+    filesfirst: bool,
+
+    /// Include hidden files
+    #[arg(short = 'h', long)]
+    show_hidden: bool,
+
+    /// Descend into symlinks that point to directories
+    #[arg(short = 'l', long)]
+    follow: bool,
+
+    /// Count symlinks to directories as directories in the report
+    #[arg(long)]
+    count_dir_links: bool,
+
+    /// Show the absolute target of symlinks and flag those leaving the tree
+    #[arg(long)]
+    resolve_links: bool,
+
+    /// Don't descend into virtual filesystems such as /proc, /sys, /dev and /run
+    #[arg(long)]
+    safe: bool,
+
+    /// Stay on the root's filesystem, not descending into other mounts
+    #[arg(short = 'x', long)]
+    one_file_system: bool,
+
+    /// Mark mount points along with their filesystem type
+    #[arg(long)]
+    mounts: bool,
+
+    /// Print the size and free space of the root's filesystem above the tree
+    #[arg(long)]
+    fs_info: bool,
+
+    /// Report the oldest and newest files
+    #[arg(long)]
+    time_range: bool,
+
+    /// Report depth, widest directory and longest path and file name
+    #[arg(long)]
+    shape_stats: bool,
+
+    /// List names with spaces, control characters or characters invalid on Windows
+    #[arg(long)]
+    check_names: bool,
+
+    /// List file names that appear in more than one directory
+    #[arg(long)]
+    find_name_clashes: bool,
+
+    /// Summarize the inodes used by each top-level entry
+    #[arg(long)]
+    inode_usage: bool,
+
+    /// Read subdirectories in parallel, which speeds up large trees
+    ///
+    /// Entries keep their order. A directory reached by two paths (bind
+    /// mounts, followed links) is listed under either of them, which can
+    /// vary from run to run.
+    #[arg(long)]
+    parallel: bool,
+
+    /// Pattern to ignore files/folders (separated by '|')
+    #[arg(short, long, value_parser = parse_patterns)]
+    ignore: Option<String>,
+
+    /// Only list files matching the pattern (separated by '|'); directories are still shown
+    #[arg(short = 'P', long = "pattern", value_name = "PATTERN", value_parser = parse_patterns)]
+    pattern: Option<String>,
+
+    /// Leave out files and folders whose name matches the regular expression
+    #[arg(long, value_name = "REGEX")]
+    ignore_regex: Option<regex::bytes::Regex>,
+
+    /// Only list files whose name matches the regular expression
+    #[arg(long, value_name = "REGEX")]
+    match_regex: Option<regex::bytes::Regex>,
+
+    /// Only list files with one of these extensions, e.g. `rs,toml,md`
+    #[arg(long, value_name = "EXTS", value_delimiter = ',')]
+    ext: Vec<String>,
+
+    /// Only list files of at least this size, e.g. `10M`
+    #[arg(long, value_name = "SIZE", value_parser = units::parse_size)]
+    min_size: Option<u64>,
+
+    /// Only list files of at most this size
+    #[arg(long, value_name = "SIZE", value_parser = units::parse_size)]
+    max_size: Option<u64>,
+
+    /// Only list files modified since then: a duration like `2d` or a date
+    #[arg(long, value_name = "WHEN", value_parser = units::parse_time_point)]
+    newer_than: Option<SystemTime>,
+
+    /// Only list files last modified before then
+    #[arg(long, value_name = "WHEN", value_parser = units::parse_time_point)]
+    older_than: Option<SystemTime>,
+
+    /// Only list entries of these types: f(ile), d(irectory), l(ink), p (FIFO), s(ocket), b(lock) or c(har device)
+    #[arg(long = "type", value_enum, value_name = "TYPES", value_delimiter = ',')]
+    types: Vec<EntryType>,
+
+    /// Match --ignore, -P, --ext, the regular expressions and --query globs regardless of case
+    #[arg(long)]
+    ignore_case: bool,
+
+    /// Disable .gitignore, .git/info/exclude and core.excludesFile processing
+    #[arg(long)]
+    no_gitignore: bool,
+
+    /// Show comments from a .rtreeinfo (or .info) file next to entries
+    #[arg(long)]
+    info: bool,
+
+    /// Show owners from CODEOWNERS where ownership changes
+    #[arg(long)]
+    codeowners: bool,
+
+    /// Color files by the age of their last git commit
+    #[arg(long)]
+    blame_age: bool,
+
+    /// Mark entries with their git status (M, A, ??, !!...)
+    #[arg(long)]
+    git_status: bool,
+
+    /// Badge directories with their project type and license ([rust] [MIT])
+    #[arg(long)]
+    badges: bool,
+
+    /// List the alternate data streams of files (NTFS only)
+    #[arg(long)]
+    streams: bool,
+
+    /// Show Finder tags and the quarantine flag (macOS only)
+    #[arg(long)]
+    mac_attrs: bool,
+
+    /// Also copy the rendered tree to the clipboard
+    #[arg(long)]
+    copy: bool,
+
+    /// Only show entries matching an expression, e.g. 'size > 10M && ext == "log"'
+    ///
+    /// Fields: name, ext, path, type (file, dir, link), size, depth and age
+    /// (time since last modification, e.g. 2d). Operators: == != < <= > >=,
+    /// ~ (glob match, ignoring case with --ignore-case), && || ! and
+    /// parentheses. The size of a directory is its own, or with --du the
+    /// total --du shows for it.
+    #[arg(long, value_parser = query::Query::parse)]
+    query: Option<query::Query>,
+
+    /// How to report entries that couldn't be read (on stderr)
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = errors::ErrorFormat::Text)]
+    errors: errors::ErrorFormat,
+
+    /// Stop with a non-zero exit code at the first entry that can't be read
+    #[arg(long)]
+    strict: bool,
+
+    /// Print help
+    #[arg(long, action = clap::ArgAction::Help)]
+    help: Option<bool>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum SampleMode {
+    First,
+    Last,
+    Random,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum EntryType {
+    #[value(name = "f")]
+    File,
+    #[value(name = "d")]
+    Directory,
+    #[value(name = "l")]
+    Link,
+    #[value(name = "p")]
+    Fifo,
+    #[value(name = "s")]
+    Socket,
+    #[value(name = "b")]
+    Block,
+    #[value(name = "c")]
+    Char,
+}
+
+impl EntryType {
+    // The letter columns::type_char gives entries of this type
+    fn letter(self) -> char {
+        match self {
+            EntryType::File => '-',
+            EntryType::Directory => 'd',
+            EntryType::Link => 'l',
+            EntryType::Fifo => 'p',
+            EntryType::Socket => 's',
+            EntryType::Block => 'b',
+            EntryType::Char => 'c',
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ColorChoice {
+    /// Color when writing to a terminal, unless NO_COLOR is set (or
+    /// CLICOLOR_FORCE is)
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the directories and files described by a tree layout
+    Apply {
+        /// Layout file, either rtree/tree output or an indented list
+        layout: PathBuf,
+
+        /// Directory in which to create the layout
+        dest: Option<PathBuf>,
+
+        /// Only print what would be created
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Refresh the tree between <!-- rtree:start --> and <!-- rtree:end --> markers
+    ///
+    /// Tree options given before the subcommand (e.g. `rtree -L 2 inject README.md`)
+    /// are used for rendering.
+    Inject {
+        /// Markdown file containing the markers
+        file: PathBuf,
+
+        /// Directory to render (defaults to the file's directory)
+        dir: Option<PathBuf>,
+
+        /// Don't write anything, exit with 1 if the snippet is out of date
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Inspect the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Create or verify a SHA-256 checksum manifest of the tree
+    Manifest {
+        #[command(subcommand)]
+        action: ManifestAction,
+    },
+
+    /// Show one tree of what was added (+), removed (-) and modified (~) from OLD to NEW
+    ///
+    /// Filters given before the subcommand (e.g. `rtree -i target diff a b`)
+    /// apply to both trees. Exits with 1 when they differ.
+    Diff {
+        /// Directory as it was
+        old: PathBuf,
+
+        /// Directory as it is
+        new: PathBuf,
+
+        /// What makes a file modified
+        #[arg(long, value_enum, default_value_t = diff::Compare::Mtime)]
+        by: diff::Compare,
+
+        /// Also show unchanged entries
+        #[arg(short = 'a', long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Report errors in the config file and print the settings it defines
+    Check,
+}
+
+#[derive(Subcommand)]
+enum ManifestAction {
+    /// Hash every file and write the manifest
+    Create {
+        /// Manifest file to write
+        file: PathBuf,
+
+        /// Directory to hash (defaults to the current directory)
+        dir: Option<PathBuf>,
+    },
+
+    /// Report files added, removed or modified since the manifest was created
+    Verify {
+        /// Manifest file to check against
+        file: PathBuf,
+
+        /// Directory to check (defaults to the current directory)
+        dir: Option<PathBuf>,
+    },
+}
+
+// State shared by the whole walk
+struct Context {
+    root: PathBuf,
+    // Absolute form of `root`, for telling where links lead
+    canonical_root: Option<PathBuf>,
+    // -I patterns, and the -P ones files have to match one of. Like the
+    // regexes they match the raw bytes of names, with --ignore-case
+    // regardless of case.
+    ignore_patterns: Vec<GlobMatcher>,
+    // Rules from .gitignore (unless --no-gitignore), .ignore and
+    // .rtreeignore files
+    ignores: ignores::Ignores,
+    match_patterns: Vec<GlobMatcher>,
+    // --ignore-regex and --match-regex, made case-insensitive by
+    // --ignore-case
+    ignore_regex: Option<regex::bytes::Regex>,
+    match_regex: Option<regex::bytes::Regex>,
+    // --query, its globs also following --ignore-case
+    query: Option<query::Query>,
+    info: Vec<info::InfoRule>,
+    codeowners: Option<codeowners::CodeOwners>,
+    // Last commit time per relative path (with --blame-age)
+    commit_times: HashMap<PathBuf, i64>,
+    // `git status --short` code per relative path (with --git-status)
+    git_statuses: HashMap<PathBuf, String>,
+    // Digest per file path (with --checksum), filled in after the walk
+    checksums: HashMap<PathBuf, String>,
+    // Filesystem type per mount point (with --mounts)
+    mount_types: HashMap<PathBuf, String>,
+    // Which icons to show, resolved from --icons
+    icons: Option<icons::IconSet>,
+    // Colors from LS_COLORS, replacing the built-in ones
+    ls_colors: Option<lscolors::LsColors>,
+    // Entries that couldn't be read, reported after the walk
+    errors: Mutex<Vec<errors::TraversalError>>,
+    // Stop at the first error instead (--strict)
+    fail_fast: Option<errors::ErrorFormat>,
+}
+
+impl Context {
+    fn record(&self, error: errors::TraversalError) {
+        if let Some(format) = self.fail_fast {
+            errors::emit(&[error], format);
+            std::process::exit(errors::EXIT_TRAVERSAL);
+        }
+        self.errors.lock().unwrap().push(error);
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    directories: usize,
+    files: usize,
+    // Entries hidden by the depth limit (with --rollup)
+    truncated: walk::Rollup,
+    // Entries left out by --sample
+    omitted: usize,
+    // Entries that couldn't be read
+    errors: usize,
+    // Bytes used by the whole tree (with --du)
+    used: Option<u64>,
+    // Lines in all text files (with --lines)
+    lines: Option<u64>,
+}
+
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.directories += other.directories;
+        self.files += other.files;
+        self.truncated.add(&other.truncated);
+        self.omitted += other.omitted;
+        self.errors += other.errors;
+        if let Some(used) = other.used {
+            self.used = Some(self.used.unwrap_or(0) + used);
+        }
+        if let Some(lines) = other.lines {
+            self.lines = Some(self.lines.unwrap_or(0) + lines);
+        }
+    }
+}
+
+// The command line program: parse the arguments, print the tree and
+// exit with a code telling how it went
+pub fn run() {
+    let args = match config::expand_args(std::env::args_os().collect()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("rtree: {}", e);
+            std::process::exit(errors::EXIT_USAGE);
+        }
+    };
+    let mut opt = Opt::parse_from(args);
+    // Snippets in docs shouldn't depend on who refreshed them
+    let fixed = opt.no_config || matches!(opt.command, Some(Command::Inject { .. }));
+    i18n::init(opt.lang.or(fixed.then_some(i18n::Lang::En)), !fixed);
+    // `config check` reports a broken config itself
+    let config = if opt.no_config || matches!(opt.command, Some(Command::Config { .. })) {
+        config::Config::default()
+    } else {
+        match config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("rtree: {}", e);
+                std::process::exit(errors::EXIT_USAGE);
+            }
+        }
+    };
+    if let Some(e) = config::parse_error(&config.defaults.args()) {
+        eprintln!("rtree: config defaults: {}", e);
+        std::process::exit(errors::EXIT_USAGE);
+    }
+    // Flags win over the config file
+    let glyph_settings = glyphs::GlyphSettings {
+        branch: opt.branch.clone().or(config.glyphs.branch),
+        last: opt.last_branch.clone().or(config.glyphs.last),
+        pipe: opt.pipe.clone().or(config.glyphs.pipe),
+        indent: opt.indent.or(config.glyphs.indent),
+    };
+    glyphs::init(opt.charset, &glyph_settings);
+    if let Some(path) = opt.theme.as_ref().or(config.theme.as_ref()) {
+        match theme::load(path) {
+            Ok(loaded) => theme::init(loaded),
+            Err(e) => {
+                eprintln!("rtree: {}", e);
+                std::process::exit(errors::EXIT_USAGE);
+            }
+        }
+    }
+    // Older Windows consoles need telling to interpret color codes
+    #[cfg(windows)]
+    let _ = colored::control::set_virtual_terminal(true);
+
+    // `auto` leaves it to colored, which checks the terminal and the
+    // NO_COLOR and CLICOLOR_FORCE variables
+    match opt.color {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+    }
+
+    #[cfg(not(windows))]
+    if opt.streams {
+        eprintln!("rtree: --streams is only supported on Windows");
+    }
+    #[cfg(not(target_os = "macos"))]
+    if opt.mac_attrs {
+        eprintln!("rtree: --mac-attrs is only supported on macOS");
+    }
+
+    if let Some(Command::Apply {
+        layout,
+        dest,
+        dry_run,
+    }) = &opt.command
+    {
+        let dest = dest.clone().unwrap_or_else(|| PathBuf::from("."));
+        if let Err(e) = scaffold::apply(layout, &dest, *dry_run) {
+            eprintln!("rtree: {}", e);
+            std::process::exit(errors::EXIT_TRAVERSAL);
+        }
+        return;
+    }
+
+    if let Some(Command::Inject { file, dir, check }) = &opt.command {
+        // The snippet is fenced as text, so only the plain tree fits
+        if structured_output(&opt) || opt.markdown.is_some() {
+            eprintln!(
+                "rtree: inject writes the plain tree; -J, --xml, --html, --markdown, --csv, --tsv, --dot, --flat and --print0 don't apply"
+            );
+            std::process::exit(errors::EXIT_USAGE);
+        }
+        match inject::inject(file, dir.as_deref(), *check, &opt) {
+            Ok(true) => {}
+            // `--check` found a stale snippet
+            Ok(false) => std::process::exit(errors::EXIT_CHECK_FAILED),
+            Err(e) => {
+                eprintln!("rtree: {}", e);
+                std::process::exit(errors::EXIT_TRAVERSAL);
+            }
+        }
+        return;
+    }
+
+    if let Some(Command::Config {
+        action: ConfigAction::Check,
+    }) = &opt.command
+    {
+        match config::check(opt.no_config, &mut io::stdout()) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(errors::EXIT_CHECK_FAILED),
+            Err(e) => {
+                eprintln!("rtree: {}", e);
+                std::process::exit(errors::EXIT_TRAVERSAL);
+            }
+        }
+        return;
+    }
+
+    if let Some(Command::Manifest { action }) = &opt.command {
+        let result = match action {
+            ManifestAction::Create { file, dir } => {
+                let dir = dir.clone().unwrap_or_else(|| PathBuf::from("."));
+                manifest::create(file, &dir, &opt)
+            }
+            ManifestAction::Verify { file, dir } => {
+                let dir = dir.clone().unwrap_or_else(|| PathBuf::from("."));
+                manifest::verify(file, &dir, &opt)
+            }
+        };
+        match result {
+            Ok(manifest::Outcome::Match) => {}
+            Ok(manifest::Outcome::Mismatch) => std::process::exit(errors::EXIT_CHECK_FAILED),
+            Ok(manifest::Outcome::Incomplete) => std::process::exit(errors::EXIT_TRAVERSAL),
+            Err(e) => {
+                eprintln!("rtree: {}", e);
+                std::process::exit(errors::EXIT_TRAVERSAL);
+            }
+        }
+        return;
+    }
+
+    if let Some(Command::Diff { old, new, by, all }) = &opt.command {
+        // As with diff(1): 1 when the trees differ, 2 when they couldn't
+        // be compared
+        match diff::diff(old, new, *by, *all, &opt) {
+            Ok(diff::Outcome::Same) => {}
+            Ok(diff::Outcome::Different) => std::process::exit(errors::EXIT_CHECK_FAILED),
+            Ok(diff::Outcome::Incomplete) => std::process::exit(errors::EXIT_USAGE),
+            Err(e) => {
+                eprintln!("rtree: {}", e);
+                std::process::exit(errors::EXIT_USAGE);
+            }
+        }
+        return;
+    }
+
+    if opt.paths.is_empty() {
+        opt.paths.push(std::env::current_dir().unwrap());
+    }
+
+    if opt.interactive {
+        let root = platform::long_path(&opt.paths[0]);
+        match browse::browse(&root, &opt) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(errors::EXIT_TRAVERSAL),
+            Err(e) => {
+                eprintln!("rtree: {}", e);
+                std::process::exit(errors::EXIT_TRAVERSAL);
+            }
+        }
+        return;
+    }
+
+    if opt.watch {
+        if let Err(e) = watch::run(&opt) {
+            eprintln!("rtree: {}", e);
+            std::process::exit(errors::EXIT_TRAVERSAL);
+        }
+        return;
+    }
+
+    let start = Instant::now();
+    // Buffered and locked once, rather than flushed at every line
+    let mut target: Box<dyn Write> = match &opt.output {
+        // Escape codes have no business in a file
+        Some(file) => {
+            if opt.color != ColorChoice::Always {
+                colored::control::set_override(false);
+            }
+            match File::create(file) {
+                Ok(file) => Box::new(io::BufWriter::new(file)),
+                Err(e) => {
+                    eprintln!("rtree: {}: {}", file.display(), e);
+                    std::process::exit(errors::EXIT_TRAVERSAL);
+                }
+            }
+        }
+        None => Box::new(io::BufWriter::new(io::stdout().lock())),
+    };
+    // With --copy the tree is captured first so it can also go to the clipboard
+    let mut captured = Vec::new();
+    let out: &mut dyn Write = if opt.copy {
+        &mut captured
+    } else {
+        &mut *target
+    };
+    let result = render(&opt, out);
+    let stats = match result {
+        Ok(stats) => stats,
+        // The reader went away (e.g. `rtree | head`), nothing left to do
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return,
+        Err(e) => {
+            // What was rendered before the error still goes out, as
+            // exit() skips dropping the buffer
+            let _ = target.flush();
+            eprintln!("rtree: {}", e);
+            std::process::exit(errors::EXIT_TRAVERSAL);
+        }
+    };
+
+    let duration = start.elapsed();
+
+    if opt.copy {
+        if target.write_all(&captured).is_err() {
+            return;
+        }
+        let text = clipboard::strip_ansi(&String::from_utf8_lossy(&captured));
+        if let Err(e) = clipboard::copy(&text) {
+            eprintln!("rtree: could not copy to clipboard: {}", e);
+        }
+    }
+    // Before anything else is printed, and before exit() skips dropping it
+    match target.flush() {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return,
+        Err(e) => {
+            eprintln!("rtree: {}", e);
+            std::process::exit(errors::EXIT_TRAVERSAL);
+        }
+    }
+
+    // Structured output has to stay parseable
+    if opt.timing && !structured_output(&opt) && opt.markdown.is_none() {
+        println!(
+            "{}",
+            i18n::fill(Msg::TimeTaken, &[&format!("{:?}", duration)])
+        );
+    }
+
+    if stats.errors > 0 {
+        std::process::exit(errors::EXIT_TRAVERSAL);
+    }
+    if filtering(&opt) && stats.directories + stats.files == 0 {
+        std::process::exit(errors::EXIT_NO_MATCH);
+    }
+}
+
+// Render every path, the way the options say
+fn render(opt: &Opt, out: &mut dyn Write) -> io::Result<Stats> {
+    render_walked(opt, &mut |path| walk_root(path, opt), out)
+}
+
+// A tree as walk_root leaves it: the context of the walk, the entries,
+// how many --sample left out and why the root couldn't be listed
+type Walked = (Context, Vec<walk::Node>, usize, Option<String>);
+
+// Render every path, getting their trees from `walker` (given the long
+// form of each path, in order) rather than walking them here, so --watch
+// can render what it walked to look for changes
+fn render_walked(
+    opt: &Opt,
+    walker: &mut dyn FnMut(&Path) -> Walked,
+    out: &mut dyn Write,
+) -> io::Result<Stats> {
+    let code_block = opt.markdown == Some(markdown::MarkdownStyle::Code);
+    if code_block {
+        colored::control::set_override(false);
+        writeln!(out, "```text")?;
+    }
+    let stats = match &opt.merge_roots {
+        Some(label) => render_merged(label, &opt.paths, opt, walker, out)?,
+        None => render_trees(&opt.paths, opt, walker, out)?,
+    };
+    if code_block {
+        writeln!(out, "```")?;
+    }
+    Ok(stats)
+}
+
+// Whether entries are left out by what they are, so an empty tree
+// means nothing matched
+fn filtering(opt: &Opt) -> bool {
+    opt.query.is_some()
+        || opt.min_depth.is_some()
+        || opt.pattern.is_some()
+        || opt.match_regex.is_some()
+        || !opt.ext.is_empty()
+        || opt.min_size.is_some()
+        || opt.max_size.is_some()
+        || opt.newer_than.is_some()
+        || opt.older_than.is_some()
+        || !opt.types.is_empty()
+}
+
+// Render each path as a tree of its own
+fn render_trees(
+    paths: &[PathBuf],
+    opt: &Opt,
+    walker: &mut dyn FnMut(&Path) -> Walked,
+    out: &mut dyn Write,
+) -> io::Result<Stats> {
+    let mut stats = Stats::default();
+    for (i, path) in paths.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let label = names::display(path.as_os_str(), opt.escape);
+        stats.add(&render_tree(path, &label, opt, walker, out)?);
+        // --strict may exit while walking the next one
+        out.flush()?;
+    }
+    Ok(stats)
+}
+
+// Render the tree below `path`, headed by `root_label`, followed by the
+// report
+fn render_tree(
+    path: &Path,
+    root_label: &str,
+    opt: &Opt,
+    walker: &mut dyn FnMut(&Path) -> Walked,
+    out: &mut dyn Write,
+) -> io::Result<Stats> {
+    let long_path = platform::long_path(path);
+    let path = long_path.as_path();
+
+    if structured_output(opt) {
+        let (ctx, nodes, omitted, _) = walker(path);
+        let mut stats = tally(&nodes, omitted, opt);
+        stats.errors = ctx.errors.lock().unwrap().len();
+        stats.used = opt.du.then(|| walk::total_size(&nodes));
+        stats.lines = opt.lines.then(|| walk::total_lines(&nodes));
+        let path = names::display(platform::plain_path(path).as_os_str(), opt.escape);
+        write_structured(root_label, Some(&path), &nodes, &stats, opt, out)?;
+        return Ok(stats);
+    }
+
+    if opt.fs_info {
+        fs_info_header(path, out)?;
+    }
+    let (ctx, nodes, omitted, error) = walker(path);
+    writeln!(out, "{}{}", root_label, error_note(error.as_deref()))?;
+
+    let mut stats = if opt.bfs {
+        list_breadth_first(&nodes, omitted, Some(&ctx.root), opt, out)?
+    } else {
+        list_contents(&nodes, omitted, &[], opt, &ctx, out)?
+    };
+    stats.errors = ctx.errors.lock().unwrap().len();
+    stats.used = opt.du.then(|| walk::total_size(&nodes));
+    stats.lines = opt.lines.then(|| walk::total_lines(&nodes));
+
+    if !opt.noreport {
+        writeln!(out, "\n{}", report_line(&stats))?;
+    }
+    write_reports(path, &nodes, opt, out)?;
+    if opt.inode_usage {
+        reports::inode_usage(path, opt.escape, out)?;
+    }
+
+    Ok(stats)
+}
+
+// Render all `paths` as the directories of a made-up root called `label`
+fn render_merged(
+    label: &str,
+    paths: &[PathBuf],
+    opt: &Opt,
+    walker: &mut dyn FnMut(&Path) -> Walked,
+    out: &mut dyn Write,
+) -> io::Result<Stats> {
+    let mut stats = Stats::default();
+    let mut walks = Vec::new();
+    for path in paths {
+        let (ctx, nodes, omitted, error) = walker(&platform::long_path(path));
+        stats.errors += ctx.errors.lock().unwrap().len();
+        match fs::metadata(&ctx.root) {
+            Ok(metadata) => {
+                let du = opt.du.then(|| metadata.len() + walk::total_size(&nodes));
+                let lines = opt.lines.then(|| walk::total_lines(&nodes));
+                let root = walk::Node {
+                    path: path.clone(),
+                    name: names::display(path.as_os_str(), opt.escape),
+                    metadata,
+                    depth: 0,
+                    children: nodes,
+                    omitted,
+                    followed: false,
+                    badge: None,
+                    rollup: None,
+                    error,
+                    du,
+                    lines,
+                };
+                walks.push((ctx, root));
+            }
+            // Already reported by the walk
+            Err(_) => continue,
+        }
+    }
+
+    if structured_output(opt) {
+        let roots: Vec<walk::Node> = walks.into_iter().map(|(_, root)| root).collect();
+        let errors = stats.errors;
+        let mut stats = tally(&roots, 0, opt);
+        stats.errors = errors;
+        stats.used = opt.du.then(|| walk::total_size(&roots));
+        stats.lines = opt.lines.then(|| walk::total_lines(&roots));
+        write_structured(label, None, &roots, &stats, opt, out)?;
+        return Ok(stats);
+    }
+
+    stats.used = opt
+        .du
+        .then(|| walks.iter().map(|(_, root)| root.size()).sum());
+    stats.lines = opt
+        .lines
+        .then(|| walks.iter().filter_map(|(_, root)| root.lines).sum());
+    writeln!(out, "{}", label)?;
+    if opt.bfs {
+        let roots: Vec<walk::Node> = walks.into_iter().map(|(_, root)| root).collect();
+        stats.add(&list_breadth_first(&roots, 0, None, opt, out)?);
+        if !opt.noreport {
+            writeln!(out, "\n{}", report_line(&stats))?;
+        }
+        return write_reports(Path::new(label), &roots, opt, out).map(|_| stats);
+    }
+
+    for (i, (ctx, root)) in walks.iter().enumerate() {
+        let is_last = i == walks.len() - 1;
+        writeln!(
+            out,
+            "{}{}{}",
+            branch_prefix(&[], is_last),
+            root.name.blue().bold(),
+            error_note(root.error.as_deref())
+        )?;
+        stats.directories += 1;
+        stats.add(&list_contents(
+            &root.children,
+            root.omitted,
+            &[is_last],
+            opt,
+            ctx,
+            out,
+        )?);
+    }
+
+    if !opt.noreport {
+        writeln!(out, "\n{}", report_line(&stats))?;
+    }
+    let roots: Vec<walk::Node> = walks.into_iter().map(|(_, root)| root).collect();
+    write_reports(Path::new(label), &roots, opt, out)?;
+    if opt.inode_usage {
+        for root in &roots {
+            reports::inode_usage(&root.path, opt.escape, out)?;
+        }
+    }
+
+    Ok(stats)
+}
+
+// Whether the tree is written in a format meant for other programs
+fn structured_output(opt: &Opt) -> bool {
+    opt.json
+        || opt.xml
+        || opt.html.is_some()
+        || opt.markdown == Some(markdown::MarkdownStyle::List)
+        || opt.csv
+        || opt.tsv
+        || opt.dot
+        || opt.flat
+        || opt.print0
+}
+
+fn write_structured(
+    root_label: &str,
+    root_path: Option<&str>,
+    nodes: &[walk::Node],
+    stats: &Stats,
+    opt: &Opt,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    if let Some(base_href) = &opt.html {
+        let root = root_path.map(Path::new);
+        let report = (!opt.noreport).then_some(stats);
+        html::write_tree(root_label, root, nodes, report, base_href, &opt.title, out)
+    } else if opt.xml {
+        xml::write_tree(root_label, nodes, stats, out)
+    } else if opt.flat || opt.print0 {
+        list_flat(nodes, if opt.print0 { '\0' } else { '\n' }, out)
+    } else if opt.dot {
+        dot::write_graph(root_label, nodes, out)
+    } else if opt.csv || opt.tsv {
+        table::write_rows(nodes, if opt.csv { ',' } else { '\t' }, out)
+    } else if opt.markdown.is_some() {
+        markdown::write_list(root_label, nodes, (!opt.noreport).then_some(stats), out)
+    } else {
+        json::write_tree(root_label, root_path, nodes, stats, out)
+    }
+}
+
+// Walk the tree below `path`, reporting what couldn't be read. Also
+// returns why `path` itself couldn't be listed.
+fn walk_root(path: &Path, opt: &Opt) -> Walked {
+    let mut walked = read_root(path, opt, build_context(path, opt));
+    errors::emit(walked.0.errors.get_mut().unwrap(), opt.errors);
+    walked
+}
+
+// Walk the tree below `path` with `ctx`, leaving what couldn't be read
+// in its errors
+fn read_root(path: &Path, opt: &Opt, mut ctx: Context) -> Walked {
+    let (nodes, omitted, error) = match walk::walk(path, opt, &ctx) {
+        Ok((nodes, omitted)) => (nodes, omitted, None),
+        Err(e) => (Vec::new(), 0, Some(errors::describe(&e))),
+    };
+    if let Some(algorithm) = opt.checksum {
+        ctx.checksums = checksum::digests(&nodes, algorithm);
+    }
+    // Threads of a parallel walk record errors in no particular order
+    let errors = ctx.errors.get_mut().unwrap();
+    if opt.parallel {
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    (ctx, nodes, omitted, error)
+}
+
+fn fs_info_header(path: &Path, out: &mut dyn Write) -> io::Result<()> {
+    if let Some(usage) = fsinfo::usage(path) {
+        // Same as df: the share of the space available to users
+        let percent = match usage.used + usage.free {
+            0 => 0,
+            available => (usage.used * 100).div_ceil(available),
+        };
+        writeln!(
+            out,
+            "{}",
+            i18n::fill(
+                Msg::Filesystem,
+                &[
+                    &units::human_size(usage.total),
+                    &units::human_size(usage.used),
+                    &percent,
+                    &units::human_size(usage.free)
+                ]
+            )
+            .dimmed()
+        )?;
+    }
+    Ok(())
+}
+
+// The reports asked for after the summary line
+fn write_reports(
+    root: &Path,
+    nodes: &[walk::Node],
+    opt: &Opt,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    if opt.time_range {
+        reports::time_range(nodes, opt.escape, out)?;
+    }
+    if opt.shape_stats {
+        reports::shape_stats(root, nodes, opt.escape, out)?;
+    }
+    if opt.check_names {
+        reports::problem_names(nodes, out)?;
+    }
+    if opt.find_name_clashes {
+        reports::name_clashes(nodes, opt.escape, out)?;
+    }
+    Ok(())
+}
+
+fn build_context(path: &Path, opt: &Opt) -> Context {
+    let globs = |patterns: &Option<String>| -> Vec<GlobMatcher> {
+        patterns
+            .iter()
+            .flat_map(|patterns| patterns.split('|'))
+            .filter_map(|p| glob(p, opt.ignore_case).ok())
+            .collect()
+    };
+
+    let info = if opt.info {
+        info::load(path)
+    } else {
+        Vec::new()
+    };
+
+    Context {
+        root: path.to_path_buf(),
+        canonical_root: fs::canonicalize(path).ok(),
+        ignore_patterns: globs(&opt.ignore),
+        ignores: ignores::Ignores::load(path, !opt.no_gitignore),
+        match_patterns: globs(&opt.pattern),
+        ignore_regex: opt.ignore_regex.as_ref().map(|re| case_folded(re, opt)),
+        match_regex: opt.match_regex.as_ref().map(|re| case_folded(re, opt)),
+        query: opt
+            .query
+            .as_ref()
+            .map(|query| query.with_case(opt.ignore_case)),
+        info,
+        codeowners: if opt.codeowners {
+            codeowners::CodeOwners::load(path)
+        } else {
+            None
+        },
+        commit_times: if opt.blame_age {
+            git::last_commit_times(path)
+        } else {
+            HashMap::new()
+        },
+        git_statuses: if opt.git_status {
+            git::statuses(path)
+        } else {
+            HashMap::new()
+        },
+        checksums: HashMap::new(),
+        mount_types: if opt.mounts {
+            fsinfo::mount_types()
+        } else {
+            HashMap::new()
+        },
+        icons: opt.icons.map(icons::IconSet::resolve),
+        ls_colors: if opt.no_config || theme::is_custom() {
+            None
+        } else {
+            lscolors::from_env()
+        },
+        errors: Mutex::new(Vec::new()),
+        fail_fast: opt.strict.then_some(opt.errors),
+    }
+}
+
+fn report_line(stats: &Stats) -> String {
+    let mut line = match stats.used {
+        Some(used) => i18n::fill(
+            Msg::UsedSummary,
+            &[&units::human_size(used), &stats.directories, &stats.files],
+        ),
+        None => i18n::fill(Msg::Summary, &[&stats.directories, &stats.files]),
+    };
+    if let Some(lines) = stats.lines {
+        line.push_str(", ");
+        line.push_str(&i18n::fill(
+            Msg::Lines,
+            &[&units::group_digits(lines as usize)],
+        ));
+    }
+    if !stats.truncated.is_empty() {
+        line.push_str(&i18n::fill(
+            Msg::BelowDepthLimit,
+            &[
+                &units::group_digits(stats.truncated.directories),
+                &units::group_digits(stats.truncated.files),
+                &units::human_size(stats.truncated.bytes),
+            ],
+        ));
+    }
+    if stats.omitted > 0 {
+        line.push_str(&i18n::fill(
+            Msg::NotSampled,
+            &[&units::group_digits(stats.omitted)],
+        ));
+    }
+    match stats.errors {
+        0 => {}
+        1 => {
+            line.push_str(", ");
+            line.push_str(i18n::tr(Msg::OneError));
+        }
+        errors => {
+            line.push_str(", ");
+            line.push_str(&i18n::fill(Msg::Errors, &[&units::group_digits(errors)]));
+        }
+    }
+    line
+}
+
+// `[error opening dir: ...]` after a directory that couldn't be listed,
+// as GNU tree shows it
+fn error_note(error: Option<&str>) -> String {
+    match error {
+        Some(error) => format!("  {}", i18n::fill(Msg::ErrorOpeningDir, &[&error]).red()),
+        None => String::new(),
+    }
+}
+
+// Check that each of the '|'-separated globs is valid, so a typo is an
+// error rather than a pattern that quietly matches nothing
+fn parse_patterns(patterns: &str) -> Result<String, String> {
+    for pattern in patterns.split('|') {
+        if let Err(e) = glob(pattern, false) {
+            return Err(format!("{} in '{}'", e.kind(), pattern));
+        }
+    }
+    Ok(patterns.to_string())
+}
+
+// A shell pattern, matched against the bytes of a name or path
+fn glob(pattern: &str, ignore_case: bool) -> Result<GlobMatcher, globset::Error> {
+    Ok(GlobBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()?
+        .compile_matcher())
+}
+
+// `re`, matching regardless of case with --ignore-case. The regexes
+// match the raw bytes of names, so `(?-u:\xff)` finds names that
+// aren't UTF-8.
+fn case_folded(re: &regex::bytes::Regex, opt: &Opt) -> regex::bytes::Regex {
+    if !opt.ignore_case {
+        return re.clone();
+    }
+    regex::bytes::RegexBuilder::new(re.as_str())
+        .case_insensitive(true)
+        .build()
+        .unwrap_or_else(|_| re.clone())
+}
+
+fn list_contents(
+    nodes: &[walk::Node],
+    omitted: usize,
+    prefixes: &[bool],
+    opt: &Opt,
+    ctx: &Context,
+    out: &mut dyn Write,
+) -> io::Result<Stats> {
+    let mut stats = Stats {
+        omitted,
+        ..Stats::default()
+    };
+
+    // The note stands in for the entries --sample left out, placed
+    // on the side they were taken from
+    let omitted_note = i18n::fill(Msg::NotShown, &[&units::group_digits(omitted)])
+        .dimmed()
+        .to_string();
+    let note_first = omitted > 0 && opt.sample_mode == SampleMode::Last;
+    let note_last = omitted > 0 && !note_first;
+    if note_first {
+        let last = nodes.is_empty();
+        writeln!(out, "{}{}", branch_prefix(prefixes, last), omitted_note)?;
+    }
+
+    let entries_len = nodes.len();
+    let theme = theme::get();
+    let colors_on = colored::control::SHOULD_COLORIZE.should_colorize();
+
+    for (i, node) in nodes.iter().enumerate() {
+        let path = &node.path;
+        let full_path;
+        let file_name = if opt.full_path {
+            full_path = names::display(platform::plain_path(path).as_os_str(), opt.escape);
+            &full_path
+        } else {
+            &node.name
+        };
+        let metadata = &node.metadata;
+
+        let is_last = i == entries_len - 1 && !note_last;
+        let mut prefix = branch_prefix(prefixes, is_last);
+        prefix.push_str(&columns::render(node, opt));
+        if let Some(set) = ctx.icons {
+            prefix.push_str(icons::icon(set, &node.name, metadata));
+        }
+
+        let display;
+        let indicator = if opt.classify {
+            columns::indicator(&node.name, metadata)
+        } else {
+            ""
+        };
+
+        let mut badge = match &node.badge {
+            Some(badge) => format!("  {}", badge.dimmed()),
+            None => String::new(),
+        };
+        badge.push_str(&error_note(node.error.as_deref()));
+
+        // Owners and annotation from the info file
+        let mut note = match &ctx.codeowners {
+            Some(codeowners) => owners_note(codeowners, &ctx.root, path),
+            None => String::new(),
+        };
+        if let Some(comment) = path
+            .strip_prefix(&ctx.root)
+            .ok()
+            .and_then(|relative| info::lookup(&ctx.info, relative, path.is_dir()))
+        {
+            note.push_str(&format!("  {}", format!("# {}", comment).dimmed()));
+        }
+
+        if let Some(lines) = node.lines {
+            let count = i18n::fill(Msg::Lines, &[&units::group_digits(lines as usize)]);
+            note.push_str(&format!("  {}", format!("({})", count).dimmed()));
+        }
+
+        if let Some(digest) = ctx.checksums.get(path) {
+            note.push_str(&format!("  {}", digest.dimmed()));
+        }
+        if opt.mime && metadata.is_file() {
+            if let Some(mime) = filetype::mime_type(path) {
+                note.push_str(&format!("  {}", format!("[{}]", mime).dimmed()));
+            }
+        }
+
+        // Staged changes in green, unstaged ones and untracked files in red
+        if let Some(code) = path
+            .strip_prefix(&ctx.root)
+            .ok()
+            .and_then(|relative| git::status_of(&ctx.git_statuses, relative))
+        {
+            let badge = match code {
+                "??" => code.red().to_string(),
+                "!!" => code.dimmed().to_string(),
+                _ => {
+                    let mut letters = code.chars();
+                    let staged = letters.next().unwrap_or(' ').to_string();
+                    let unstaged = letters.next().unwrap_or(' ').to_string();
+                    format!("{}{}", staged.green(), unstaged.red())
+                }
+            };
+            note.push_str(&format!("  {}", badge));
+        }
+
+        // Entries that just appeared or changed, while --watch redraws
+        if opt.watch {
+            let change = match watch::change_of(path) {
+                Some(watch::Change::Added) => Some(Msg::New),
+                Some(watch::Change::Modified) => Some(Msg::Modified),
+                None => None,
+            };
+            if let Some(change) = change {
+                note.push_str(&format!("  {}", i18n::tr(change).black().on_yellow()));
+            }
+        }
+
+        // Alternate data streams hidden behind the file
+        if opt.streams && metadata.is_file() {
+            for (name, size) in streams::alternate_streams(path) {
+                note.push_str(&format!(
+                    "  {}",
+                    format!("[{} {}]", name, units::human_size(size)).magenta()
+                ));
+            }
+        }
+
+        // Finder tags and quarantine flag
+        if opt.mac_attrs {
+            let attrs = macattrs::read(path);
+            for (tag, color) in &attrs.tags {
+                note.push_str(&format!(
+                    "  {}",
+                    format!("●{}", tag).color(macattrs::tag_color(*color))
+                ));
+            }
+            match attrs.quarantine.as_deref() {
+                Some("") => {
+                    note.push_str(&format!("  {}", i18n::tr(Msg::Quarantined).red().bold()))
+                }
+                Some(agent) => note.push_str(&format!(
+                    "  {}",
+                    i18n::fill(Msg::QuarantinedBy, &[&agent]).red().bold()
+                )),
+                None => {}
+            }
+        }
+
+        // Symbolic link
+        if metadata.file_type().is_symlink() {
+            let mut target = match platform::link_target(path) {
+                Ok(t) => t,
+                Err(_) => PathBuf::from(i18n::tr(Msg::Unreadable)),
+            };
+            let mut location = "";
+            if opt.resolve_links {
+                match fs::canonicalize(path) {
+                    Ok(resolved) => {
+                        let inside = ctx
+                            .canonical_root
+                            .as_ref()
+                            .is_some_and(|root| resolved.starts_with(root));
+                        if !inside {
+                            location = i18n::tr(Msg::OutsideRoot);
+                        }
+                        target = resolved;
+                    }
+                    Err(_) => location = i18n::tr(Msg::Broken),
+                }
+            }
+            display = match &ctx.ls_colors {
+                Some(ls) => format!(
+                    "{}{} -> {}",
+                    ls.paint(file_name, path, metadata),
+                    indicator,
+                    ls.paint_target(&names::display(target.as_os_str(), opt.escape), path)
+                ),
+                None => format!(
+                    "{}{} -> {}",
+                    theme.symlink.paint(file_name),
+                    indicator,
+                    theme
+                        .link_target
+                        .paint(&names::display(target.as_os_str(), opt.escape))
+                ),
+            };
+            if badge.is_empty() && !location.is_empty() {
+                badge = format!("  {}", location.dimmed());
+            }
+
+            writeln!(out, "{}{}{}{}", prefix, display, badge, note)?;
+
+            // Followed links count as the directory they lead to
+            if node.followed {
+                stats.directories += 1;
+                let mut new_prefixes = prefixes.to_vec();
+                new_prefixes.push(is_last);
+                let sub_stats =
+                    list_contents(&node.children, node.omitted, &new_prefixes, opt, ctx, out)?;
+                stats.add(&sub_stats);
+            } else if opt.count_dir_links && path.is_dir() {
+                stats.directories += 1;
+            } else {
+                stats.files += 1;
+            }
+
+        // Directory
+        } else if node.is_dir() {
+            display = match &ctx.ls_colors {
+                Some(ls) => ls.paint(file_name, path, metadata),
+                None => theme.directory.paint(file_name),
+            };
+            let rollup = match &node.rollup {
+                Some(rollup) if !rollup.is_empty() => {
+                    stats.truncated.add(rollup);
+                    format!(
+                        "  {}",
+                        i18n::fill(
+                            Msg::Rollup,
+                            &[
+                                &units::group_digits(rollup.files),
+                                &units::group_digits(rollup.directories),
+                                &units::human_size(rollup.bytes),
+                            ],
+                        )
+                        .dimmed()
+                    )
+                }
+                _ => String::new(),
+            };
+            let project = if opt.badges {
+                badges::project_badges(path)
+                    .iter()
+                    .map(|b| format!(" {}", b.cyan()))
+                    .collect::<String>()
+            } else {
+                String::new()
+            };
+            writeln!(
+                out,
+                "{}{}{}{}{}{}{}",
+                prefix, display, indicator, project, rollup, badge, note
+            )?;
+
+            stats.directories += 1;
+            let mut new_prefixes = prefixes.to_vec();
+            new_prefixes.push(is_last);
+            let sub_stats =
+                list_contents(&node.children, node.omitted, &new_prefixes, opt, ctx, out)?;
+            stats.add(&sub_stats);
+
+        // FIFO, socket or device, labelled with what it is
+        } else if let Some(label) = special_label(metadata) {
+            display = match &ctx.ls_colors {
+                Some(ls) => ls.paint(file_name, path, metadata),
+                None => match columns::type_char(metadata.file_type()) {
+                    'p' => theme.fifo.paint(file_name),
+                    's' => theme.socket.paint(file_name),
+                    _ => theme.device.paint(file_name),
+                },
+            };
+            let label = format!("  {}", i18n::tr(label).dimmed());
+            writeln!(out, "{}{}{}{}{}", prefix, display, indicator, label, note)?;
+            stats.files += 1;
+
+        // File colored by the age of its last commit
+        } else if let Some(&time) = path
+            .strip_prefix(&ctx.root)
+            .ok()
+            .and_then(|relative| ctx.commit_times.get(relative))
+        {
+            display = blame_color(file_name, time);
+            writeln!(out, "{}{}{}{}", prefix, display, indicator, note)?;
+            stats.files += 1;
+
+        // Script, colored by the interpreter in its #! line (before LS_COLORS,
+        // which can't tell scripts without an extension apart). Files are
+        // only opened when colors are on, and only regular ones: reading a
+        // FIFO would block. The name looked at is the entry's own, even
+        // with -f.
+        } else if let Some(interpreter) = (colors_on && metadata.is_file())
+            .then(|| filetype::shebang_interpreter(path, &node.name))
+            .flatten()
+        {
+            display = file_name
+                .color(filetype::interpreter_color(&interpreter))
+                .to_string();
+            writeln!(out, "{}{}{}{}", prefix, display, indicator, note)?;
+            stats.files += 1;
+
+        // Any other entry, the way ls would color it
+        } else if let Some(ls) = &ctx.ls_colors {
+            display = ls.paint(file_name, path, metadata);
+            writeln!(out, "{}{}{}{}", prefix, display, indicator, note)?;
+            stats.files += 1;
+
+        // Executable file
+        } else if platform::is_executable(&node.name, metadata) {
+            display = theme.executable.paint(file_name);
+            writeln!(out, "{}{}{}{}", prefix, display, indicator, note)?;
+            stats.files += 1;
+
+        // Regular file, colored by its extension
+        } else {
+            display = theme.file_style(file_name).paint(file_name);
+
+            writeln!(out, "{}{}{}{}", prefix, display, indicator, note)?;
+            stats.files += 1;
+        }
+    }
+
+    if note_last {
+        writeln!(out, "{}{}", branch_prefix(prefixes, true), omitted_note)?;
+    }
+
+    Ok(stats)
+}
+
+// All entries at depth 1, then depth 2 and so on, each as its path
+// below `root` (or as is, without a root)
+fn list_breadth_first(
+    nodes: &[walk::Node],
+    omitted: usize,
+    root: Option<&Path>,
+    opt: &Opt,
+    out: &mut dyn Write,
+) -> io::Result<Stats> {
+    let mut stats = Stats {
+        omitted,
+        ..Stats::default()
+    };
+
+    let mut queue: VecDeque<&walk::Node> = nodes.iter().collect();
+    while let Some(node) = queue.pop_front() {
+        let relative = root
+            .and_then(|root| node.path.strip_prefix(root).ok())
+            .unwrap_or(&node.path);
+        let relative = names::display(relative.as_os_str(), opt.escape);
+
+        if node.is_dir() || node.followed {
+            writeln!(out, "{}", format!("{}/", relative).blue().bold())?;
+            stats.directories += 1;
+        } else {
+            writeln!(out, "{}", relative)?;
+            stats.files += 1;
+        }
+        if let Some(rollup) = &node.rollup {
+            stats.truncated.add(rollup);
+        }
+        stats.omitted += node.omitted;
+        queue.extend(&node.children);
+    }
+
+    Ok(stats)
+}
+
+// Every entry's path followed by `terminator`, parents before children.
+// Names that aren't UTF-8 still reach xargs intact.
+fn list_flat(nodes: &[walk::Node], terminator: char, out: &mut dyn Write) -> io::Result<()> {
+    for node in nodes {
+        out.write_all(&platform::path_bytes(&node.path))?;
+        write!(out, "{}", terminator)?;
+        list_flat(&node.children, terminator, out)?;
+    }
+    Ok(())
+}
+
+// Count the entries of the tree the way the text output does, for the
+// formats that don't go through list_contents
+fn tally(nodes: &[walk::Node], omitted: usize, opt: &Opt) -> Stats {
+    let mut stats = Stats {
+        omitted,
+        ..Stats::default()
+    };
+    for node in nodes {
+        let links_to_dir = opt.count_dir_links && node.path.is_dir();
+        if node.is_dir() || node.followed || links_to_dir {
+            stats.directories += 1;
+        } else {
+            stats.files += 1;
+        }
+        if let Some(rollup) = &node.rollup {
+            stats.truncated.add(rollup);
+        }
+        stats.add(&tally(&node.children, node.omitted, opt));
+    }
+    stats
+}
+
+// What a special file is, None for anything else
+fn special_label(metadata: &fs::Metadata) -> Option<Msg> {
+    match columns::type_char(metadata.file_type()) {
+        'p' => Some(Msg::Fifo),
+        's' => Some(Msg::Socket),
+        'b' => Some(Msg::BlockDevice),
+        'c' => Some(Msg::CharDevice),
+        _ => None,
+    }
+}
+
+// Fresh commits are green, turning yellow and then red with age
+fn blame_color(name: &str, commit_time: i64) -> String {
+    const DAY: i64 = 24 * 60 * 60;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    match now - commit_time {
+        age if age < 7 * DAY => name.bright_green().to_string(),
+        age if age < 30 * DAY => name.green().to_string(),
+        age if age < 180 * DAY => name.yellow().to_string(),
+        age if age < 365 * DAY => name.bright_red().to_string(),
+        _ => name.red().to_string(),
+    }
+}
+
+// Owners of an entry, shown only where they differ from the parent's so
+// ownership boundaries and gaps stand out
+fn owners_note(codeowners: &codeowners::CodeOwners, root: &Path, path: &Path) -> String {
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative,
+        Err(_) => return String::new(),
+    };
+    let owners = codeowners.owners(relative, path.is_dir());
+
+    let parent = relative.parent().filter(|p| !p.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        if codeowners.owners(parent, true) == owners {
+            return String::new();
+        }
+    }
+
+    match owners {
+        Some(owners) => format!("  {}", owners.yellow()),
+        None => format!("  {}", i18n::tr(Msg::Unowned).dimmed()),
+    }
+}
+
+// Build the branch glyphs in front of an entry
+fn branch_prefix(prefixes: &[bool], is_last: bool) -> String {
+    let glyphs = glyphs::get();
+    let mut prefix = String::new();
+    for &last in prefixes.iter() {
+        if last {
+            prefix.push_str(&glyphs.blank);
+        } else {
+            prefix.push_str(&glyphs.pipe);
+        }
+    }
+    if is_last {
+        prefix.push_str(&glyphs.last);
+    } else {
+        prefix.push_str(&glyphs.branch);
+    }
+    prefix
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Colors from the LS_COLORS variable (as set up by dircolors), applied
+// the way ls applies them
+pub struct LsColors {
+    // Two-letter keys: di, ln, ex, or...
+    types: HashMap<String, String>,
+    // `*.tar=01;31` and friends, in the order given
+    suffixes: Vec<(String, String)>,
+}
+
+pub fn from_env() -> Option<LsColors> {
+    let value = env::var("LS_COLORS").ok().filter(|v| !v.is_empty())?;
+    let mut colors = LsColors {
+        types: HashMap::new(),
+        suffixes: Vec::new(),
+    };
+    for rule in value.split(':') {
+        let Some((key, code)) = rule.split_once('=') else {
+            continue;
+        };
+        match key.strip_prefix('*') {
+            Some(suffix) => colors.suffixes.push((suffix.to_string(), code.to_string())),
+            None => {
+                colors.types.insert(key.to_string(), code.to_string());
+            }
+        }
+    }
+    Some(colors)
+}
+
+impl LsColors {
+    // `name` in the color ls would give the entry at `path`
+    pub fn paint(&self, name: &str, path: &Path, metadata: &fs::Metadata) -> String {
+        match self.style(name, path, metadata) {
+            Some(code) => paint(code, name),
+            None => name.to_string(),
+        }
+    }
+
+    // Color of a link's target, `mi` when it doesn't exist
+    pub fn paint_target(&self, target: &str, path: &Path) -> String {
+        let code = match fs::metadata(path) {
+            Ok(metadata) => self.style(target, path, &metadata),
+            Err(_) => self.types.get("mi").map(String::as_str),
+        };
+        match code {
+            Some(code) => paint(code, target),
+            None => target.to_string(),
+        }
+    }
+
+    fn style(&self, name: &str, path: &Path, metadata: &fs::Metadata) -> Option<&str> {
+        let get = |key: &str| self.types.get(key).map(String::as_str);
+        let file_type = metadata.file_type();
+        let mode = mode(metadata);
+
+        if file_type.is_symlink() {
+            return match fs::metadata(path) {
+                Err(_) => get("or").or(get("ln")),
+                // ln=target colors links like what they point to
+                Ok(target) if get("ln") == Some("target") => self.style(name, path, &target),
+                Ok(_) => get("ln"),
+            };
+        }
+        if file_type.is_dir() {
+            let sticky = mode & 0o1000 != 0;
+            let other_writable = mode & 0o002 != 0;
+            return match (sticky, other_writable) {
+                (true, true) => get("tw").or(get("di")),
+                (false, true) => get("ow").or(get("di")),
+                (true, false) => get("st").or(get("di")),
+                (false, false) => get("di"),
+            };
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_fifo() {
+                return get("pi");
+            }
+            if file_type.is_socket() {
+                return get("so");
+            }
+            if file_type.is_block_device() {
+                return get("bd");
+            }
+            if file_type.is_char_device() {
+                return get("cd");
+            }
+        }
+
+        if mode & 0o4000 != 0 && get("su").is_some() {
+            return get("su");
+        }
+        if mode & 0o2000 != 0 && get("sg").is_some() {
+            return get("sg");
+        }
+        if mode & 0o111 != 0 && get("ex").is_some() {
+            return get("ex");
+        }
+        // Exact case first, as ls does, then ignoring it
+        let lower = name.to_lowercase();
+        self.suffixes
+            .iter()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .or_else(|| {
+                self.suffixes
+                    .iter()
+                    .find(|(suffix, _)| lower.ends_with(&suffix.to_lowercase()))
+            })
+            .map(|(_, code)| code.as_str())
+            .or(get("fi"))
+    }
+}
+
+// Permission bits, none outside Unix
+fn mode(metadata: &fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if !colored::control::SHOULD_COLORIZE.should_colorize() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
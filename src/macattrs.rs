@@ -0,0 +1,155 @@
+use colored::Color;
+use std::path::Path;
+
+#[derive(Default)]
+pub struct MacAttrs {
+    // Finder tags with their color index (0 = no color)
+    pub tags: Vec<(String, u8)>,
+    // The agent that downloaded the file, set when it is quarantined
+    pub quarantine: Option<String>,
+}
+
+// Finder tags and quarantine flag of `path`, read from its extended
+// attributes. Symbolic links are not followed.
+#[cfg(target_os = "macos")]
+pub fn read(path: &Path) -> MacAttrs {
+    let tags = xattr(path, "com.apple.metadata:_kMDItemUserTags")
+        .map(|plist| {
+            bplist_strings(&plist)
+                .into_iter()
+                .map(|tag| match tag.split_once('\n') {
+                    Some((name, color)) => (name.to_string(), color.parse().unwrap_or(0)),
+                    None => (tag, 0),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // flags;timestamp;agent;uuid
+    let quarantine = xattr(path, "com.apple.quarantine").map(|value| {
+        String::from_utf8_lossy(&value)
+            .split(';')
+            .nth(2)
+            .unwrap_or("")
+            .to_string()
+    });
+
+    MacAttrs { tags, quarantine }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn read(_path: &Path) -> MacAttrs {
+    MacAttrs::default()
+}
+
+// Color Finder uses for the tag color index
+pub fn tag_color(index: u8) -> Color {
+    match index {
+        1 => Color::BrightBlack,
+        2 => Color::Green,
+        3 => Color::Magenta,
+        4 => Color::Blue,
+        5 => Color::Yellow,
+        6 => Color::Red,
+        7 => Color::BrightRed,
+        _ => Color::White,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn xattr(path: &Path, name: &str) -> Option<Vec<u8>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let c_name = CString::new(name).ok()?;
+    let get = |buf: *mut libc::c_void, size: usize| unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            buf,
+            size,
+            0,
+            libc::XATTR_NOFOLLOW,
+        )
+    };
+
+    // Ask for the size first, then fetch the value
+    let size = get(std::ptr::null_mut(), 0);
+    if size < 0 {
+        return None;
+    }
+    let mut value = vec![0u8; size as usize];
+    let read = get(value.as_mut_ptr() as *mut libc::c_void, value.len());
+    if read < 0 {
+        return None;
+    }
+    value.truncate(read as usize);
+    Some(value)
+}
+
+// Every string object of a binary property list. The tag list is a
+// plain array of strings, so there's no need to follow the references.
+#[cfg(target_os = "macos")]
+fn bplist_strings(data: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    if data.len() < 40 || !data.starts_with(b"bplist00") {
+        return strings;
+    }
+
+    let trailer = &data[data.len() - 32..];
+    let offset_size = trailer[6] as usize;
+    let objects = read_uint(&trailer[8..16]);
+    let table = read_uint(&trailer[24..32]);
+
+    for i in 0..objects {
+        let start = table + i * offset_size;
+        let offset = match data.get(start..start + offset_size) {
+            Some(bytes) => read_uint(bytes),
+            None => break,
+        };
+        let marker = match data.get(offset) {
+            Some(&marker) => marker,
+            None => continue,
+        };
+
+        // A length of 0xf means the real length follows as an int object
+        let (len, body) = match marker & 0x0f {
+            0x0f => {
+                let width = 1 << (data.get(offset + 1).copied().unwrap_or(0) & 0x0f);
+                match data.get(offset + 2..offset + 2 + width) {
+                    Some(bytes) => (read_uint(bytes), offset + 2 + width),
+                    None => continue,
+                }
+            }
+            len => (len as usize, offset + 1),
+        };
+
+        match marker >> 4 {
+            // ASCII string
+            0x5 => {
+                if let Some(bytes) = data.get(body..body + len) {
+                    strings.push(String::from_utf8_lossy(bytes).into_owned());
+                }
+            }
+            // UTF-16 big-endian string
+            0x6 => {
+                if let Some(bytes) = data.get(body..body + len * 2) {
+                    let units: Vec<u16> = bytes
+                        .chunks(2)
+                        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                        .collect();
+                    strings.push(String::from_utf16_lossy(&units));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    strings
+}
+
+#[cfg(target_os = "macos")]
+fn read_uint(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |n, &b| (n << 8) | b as usize)
+}
@@ -0,0 +1,264 @@
+use colored::*;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::checksum::{self, Algorithm};
+use crate::errors::{self, TraversalError};
+use crate::i18n::{self, Msg};
+use crate::walk::read_entries;
+use crate::{build_context, names, Context, Opt};
+
+// How the tree compares to the manifest
+#[derive(Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Match,
+    Mismatch,
+    // Some directories or files couldn't be read, and are reported
+    Incomplete,
+}
+
+// The digests of a tree, keyed by relative path, and the relative
+// paths of what couldn't be read
+struct Hashed {
+    sums: BTreeMap<OsString, String>,
+    unread: Vec<OsString>,
+}
+
+// Write a `sha256sum` compatible manifest of every file below `dir`.
+// What couldn't be read is left out, making the outcome Incomplete.
+pub fn create(manifest: &Path, dir: &Path, opt: &Opt) -> io::Result<Outcome> {
+    let Hashed { sums, unread } = hash_tree(manifest, dir, opt)?;
+
+    let mut out = io::BufWriter::new(File::create(manifest)?);
+    for (path, digest) in &sums {
+        write_line(&mut out, digest, &names::os_bytes(path))?;
+    }
+    out.flush()?;
+
+    println!(
+        "{}",
+        i18n::fill(Msg::FilesWritten, &[&sums.len(), &manifest.display()])
+    );
+    Ok(if unread.is_empty() {
+        Outcome::Match
+    } else {
+        Outcome::Incomplete
+    })
+}
+
+// Compare the tree below `dir` against `manifest`, printing every
+// difference. Files below what couldn't be read aren't taken as removed.
+pub fn verify(manifest: &Path, dir: &Path, opt: &Opt) -> io::Result<Outcome> {
+    let expected = parse_manifest(&fs::read(manifest)?).map_err(|lineno| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            i18n::fill(Msg::MalformedManifest, &[&manifest.display(), &lineno]),
+        )
+    })?;
+    let Hashed {
+        sums: actual,
+        unread,
+    } = hash_tree(manifest, dir, opt)?;
+    let unread_bytes: Vec<_> = unread.iter().map(|path| names::os_bytes(path)).collect();
+    let was_read = |path: &OsString| {
+        let path = names::os_bytes(path);
+        !unread_bytes.iter().any(|unread| {
+            path.strip_prefix(&unread[..])
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(b"/"))
+        })
+    };
+
+    let (mut added, mut removed, mut modified) = (0, 0, 0);
+    let [removed_label, modified_label, added_label] =
+        i18n::padded([Msg::RemovedFile, Msg::ModifiedFile, Msg::AddedFile]);
+
+    let shown = |path: &OsString| names::display(path, opt.escape);
+    for (path, digest) in &expected {
+        match actual.get(path) {
+            None if !was_read(path) => {}
+            None => {
+                println!("{} {}", removed_label.red(), shown(path));
+                removed += 1;
+            }
+            Some(current) if current != digest => {
+                println!("{} {}", modified_label.yellow(), shown(path));
+                modified += 1;
+            }
+            Some(_) => {}
+        }
+    }
+    for path in actual.keys().filter(|path| !expected.contains_key(*path)) {
+        println!("{} {}", added_label.green(), shown(path));
+        added += 1;
+    }
+
+    println!(
+        "\n{}",
+        i18n::fill(
+            Msg::FilesChecked,
+            &[&actual.len(), &added, &removed, &modified]
+        )
+    );
+    Ok(if !unread.is_empty() {
+        Outcome::Incomplete
+    } else if added + removed + modified > 0 {
+        Outcome::Mismatch
+    } else {
+        Outcome::Match
+    })
+}
+
+// Hash every regular file in one walk. Directories and files that
+// can't be read are reported on stderr, as the tree does.
+fn hash_tree(manifest: &Path, dir: &Path, opt: &Opt) -> io::Result<Hashed> {
+    let mut ctx = build_context(dir, opt);
+    // The manifest must not end up listing itself
+    let skip = fs::canonicalize(manifest).ok();
+
+    let mut files = Vec::new();
+    collect_files(dir, 0, opt, &ctx, skip.as_deref(), &mut files);
+
+    let hashes: Vec<(&PathBuf, io::Result<String>)> = files
+        .par_iter()
+        .map(|path| (path, hash_file(path)))
+        .collect();
+    let mut sums = BTreeMap::new();
+    for (path, hash) in hashes {
+        match hash {
+            Ok(digest) => {
+                sums.insert(relative(dir, path), digest);
+            }
+            Err(e) => ctx.record(TraversalError::io(path, &e)),
+        }
+    }
+
+    let errors = ctx.errors.get_mut().unwrap();
+    errors::emit(errors, opt.errors);
+    let unread = errors
+        .iter()
+        .map(|error| relative(dir, &error.path))
+        .collect();
+    Ok(Hashed { sums, unread })
+}
+
+// `path` below `dir`, with '/' between the names whatever their bytes
+fn relative(dir: &Path, path: &Path) -> OsString {
+    let mut relative = OsString::new();
+    for (i, name) in path.strip_prefix(dir).unwrap_or(path).iter().enumerate() {
+        if i > 0 {
+            relative.push("/");
+        }
+        relative.push(name);
+    }
+    relative
+}
+
+fn collect_files(
+    dir: &Path,
+    depth: usize,
+    opt: &Opt,
+    ctx: &Context,
+    skip: Option<&Path>,
+    files: &mut Vec<PathBuf>,
+) {
+    if let Some(max_depth) = opt.max_depth {
+        if depth >= max_depth {
+            return;
+        }
+    }
+
+    for entry in read_entries(dir, opt, ctx).unwrap_or_default() {
+        let path = entry.path();
+        let file_type = match entry.file_type() {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+
+        if file_type.is_dir() {
+            collect_files(&path, depth + 1, opt, ctx, skip, files);
+        } else if file_type.is_file() {
+            if skip.is_some() && fs::canonicalize(&path).ok().as_deref() == skip {
+                continue;
+            }
+            files.push(path);
+        }
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    checksum::digest(path, Algorithm::Sha256)
+}
+
+// Like sha256sum, names with a backslash or a line break are escaped,
+// and their line starts with a backslash to say so
+fn write_line(out: &mut dyn Write, digest: &str, path: &[u8]) -> io::Result<()> {
+    if !path.iter().any(|b| b"\\\n\r".contains(b)) {
+        write!(out, "{}  ", digest)?;
+        out.write_all(path)?;
+        return writeln!(out);
+    }
+
+    write!(out, "\\{}  ", digest)?;
+    for &byte in path {
+        match byte {
+            b'\\' => out.write_all(b"\\\\")?,
+            b'\n' => out.write_all(b"\\n")?,
+            b'\r' => out.write_all(b"\\r")?,
+            _ => out.write_all(&[byte])?,
+        }
+    }
+    writeln!(out)
+}
+
+fn unescape(path: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.iter();
+    while let Some(&byte) = iter.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        bytes.push(match iter.next()? {
+            b'\\' => b'\\',
+            b'n' => b'\n',
+            b'r' => b'\r',
+            _ => return None,
+        });
+    }
+    Some(bytes)
+}
+
+// Accepts both the text (`digest  path`) and binary (`digest *path`)
+// forms written by sha256sum. Errors carry the offending line number.
+fn parse_manifest(text: &[u8]) -> Result<BTreeMap<OsString, String>, usize> {
+    let mut sums = BTreeMap::new();
+
+    for (lineno, line) in text.split(|&b| b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.trim_ascii().is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        let (escaped, line) = match line.strip_prefix(b"\\") {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let space = line.iter().position(|&b| b == b' ').ok_or(lineno + 1)?;
+        let digest = std::str::from_utf8(&line[..space]).map_err(|_| lineno + 1)?;
+        let path = &line[space + 1..];
+        let path = path
+            .strip_prefix(b" ")
+            .or_else(|| path.strip_prefix(b"*"))
+            .ok_or(lineno + 1)?;
+        let path = match escaped {
+            true => unescape(path).ok_or(lineno + 1)?,
+            false => path.to_vec(),
+        };
+        sums.insert(names::from_bytes(&path), digest.to_lowercase());
+    }
+
+    Ok(sums)
+}
@@ -0,0 +1,58 @@
+use clap::ValueEnum;
+use std::io::{self, Write};
+
+use crate::walk::Node;
+use crate::{report_line, Stats};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum MarkdownStyle {
+    /// A nested bullet list
+    List,
+    /// The usual tree, uncolored, in a fenced code block
+    Code,
+}
+
+// Write the tree as a nested bullet list, directories marked with a
+// trailing slash, followed by the report unless `stats` is None
+pub fn write_list(
+    root_label: &str,
+    nodes: &[Node],
+    stats: Option<&Stats>,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "- {}", code_span(&format!("{}/", root_label)))?;
+    write_nodes(nodes, 1, out)?;
+    match stats {
+        Some(stats) => writeln!(out, "\n{}", report_line(stats)),
+        None => Ok(()),
+    }
+}
+
+fn write_nodes(nodes: &[Node], level: usize, out: &mut dyn Write) -> io::Result<()> {
+    for node in nodes {
+        let name = if node.is_dir() || node.followed {
+            format!("{}/", node.name)
+        } else {
+            node.name.clone()
+        };
+        writeln!(out, "{}- {}", "  ".repeat(level), code_span(&name))?;
+        write_nodes(&node.children, level + 1, out)?;
+    }
+    Ok(())
+}
+
+// Names go in code spans so `*`, `_` and friends stay literal. A span
+// holding backticks needs a longer run of them as its fence.
+fn code_span(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    if longest == 0 {
+        return format!("`{}`", text);
+    }
+    let fence = "`".repeat(longest + 1);
+    format!("{} {} {}", fence, text, fence)
+}
@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write;
+
+// `name` for printing. Bytes that aren't UTF-8 become `\xNN` rather
+// than U+FFFD, so different names never print the same. With `escape`
+// (-b) control characters are escaped as well, and backslashes doubled,
+// which makes every name unambiguous and keeps it on one line.
+pub fn display(name: &OsStr, escape: bool) -> String {
+    let bytes = os_bytes(name);
+    if !escape {
+        if let Ok(name) = std::str::from_utf8(&bytes) {
+            return name.to_string();
+        }
+    }
+
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' if escape => text.push_str("\\\\"),
+                '\n' if escape => text.push_str("\\n"),
+                '\t' if escape => text.push_str("\\t"),
+                '\r' if escape => text.push_str("\\r"),
+                c if escape && c.is_control() => {
+                    let _ = write!(text, "\\u{{{:x}}}", c as u32);
+                }
+                c => text.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(text, "\\x{:02x}", byte);
+        }
+    }
+    text
+}
+
+// The bytes of `name`: as stored on Unix, UTF-8 elsewhere (where names
+// that can't be converted lose the odd character)
+pub fn os_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Cow::Borrowed(name.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        match name.to_string_lossy() {
+            Cow::Borrowed(name) => Cow::Borrowed(name.as_bytes()),
+            Cow::Owned(name) => Cow::Owned(name.into_bytes()),
+        }
+    }
+}
+
+// The name stored as `bytes`, as returned by `os_bytes`
+pub fn from_bytes(bytes: &[u8]) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        OsStr::from_bytes(bytes).to_os_string()
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8_lossy(bytes).into_owned().into()
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Whether the entry is hidden: a dotfile everywhere, plus anything with
+// the Hidden attribute on Windows
+pub fn is_hidden(entry: &fs::DirEntry) -> bool {
+    if entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+        if let Ok(metadata) = entry.metadata() {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+
+    false
+}
+
+// The file behind `metadata` as (device, inode), for telling when two
+// paths lead to the same directory. Windows has no inode numbers in std,
+// so there the canonical path stands in for the inode and its drive or
+// share for the device.
+pub fn file_id(path: &Path, metadata: &fs::Metadata) -> (u64, u64) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let _ = path;
+        (metadata.dev(), metadata.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let _ = metadata;
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let hash = |value: &dyn Fn(&mut DefaultHasher)| {
+            let mut hasher = DefaultHasher::new();
+            value(&mut hasher);
+            hasher.finish()
+        };
+        let volume = hash(&|h| canonical.components().next().hash(h));
+        (volume, hash(&|h| canonical.hash(h)))
+    }
+}
+
+// Whether the file called `name` can be run: any execute bit on Unix,
+// an extension listed in PATHEXT (.exe, .bat...) on Windows
+pub fn is_executable(name: &str, metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = name;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(windows)]
+    {
+        let extensions =
+            std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        let ext = match name.rsplit_once('.') {
+            Some((_, ext)) if metadata.is_file() => ext,
+            _ => return false,
+        };
+        extensions
+            .split(';')
+            .any(|known| known.trim_start_matches('.').eq_ignore_ascii_case(ext))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (name, metadata);
+        false
+    }
+}
+
+// Where a symbolic link (or, on Windows, a junction) points. Junction
+// targets come back in the verbatim `\\?\C:\...` form, which is noise
+// when printed.
+pub fn link_target(path: &Path) -> std::io::Result<PathBuf> {
+    let target = fs::read_link(path)?;
+
+    #[cfg(windows)]
+    if let Some(plain) = target.to_str().and_then(|t| t.strip_prefix(r"\\?\")) {
+        if !plain.starts_with("UNC\\") {
+            return Ok(PathBuf::from(plain));
+        }
+    }
+
+    Ok(target)
+}
+
+// Windows refuses paths longer than MAX_PATH (260) unless they're in the
+// verbatim `\\?\` form, which also turns off `.`/`..` and `/` handling,
+// so the walk root is made absolute and verbatim up front. Elsewhere
+// paths are used as given.
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    if let Ok(absolute) = std::path::absolute(path) {
+        let absolute = absolute.to_string_lossy().into_owned();
+        if absolute.starts_with(r"\\?\") {
+            return PathBuf::from(absolute);
+        }
+        // \\server\share becomes \\?\UNC\server\share
+        if let Some(share) = absolute.strip_prefix(r"\\") {
+            return PathBuf::from(format!(r"\\?\UNC\{}", share));
+        }
+        return PathBuf::from(format!(r"\\?\{}", absolute));
+    }
+
+    path.to_path_buf()
+}
+
+// `path` without the verbatim prefix added by `long_path`
+pub fn plain_path(path: &Path) -> &Path {
+    #[cfg(windows)]
+    if let Some(plain) = path.to_str().and_then(|p| p.strip_prefix(r"\\?\")) {
+        return Path::new(plain);
+    }
+
+    path
+}
+
+// `path` as bytes for output: as is on Unix, where names needn't be
+// UTF-8, and without the verbatim prefix elsewhere
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    }
+    #[cfg(not(unix))]
+    {
+        plain_path(path).display().to_string().into_bytes()
+    }
+}
+
+// A key that sorts `name` the way the user's locale (LC_COLLATE, LC_ALL
+// or LANG) orders text, accents and non-Latin scripts included. Elsewhere
+// than Unix names are only compared regardless of case.
+pub fn collation_key(name: &std::ffi::OsStr) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        use std::sync::Once;
+
+        // The C library starts in the "C" locale, which orders by byte
+        static INIT: Once = Once::new();
+        INIT.call_once(|| unsafe {
+            libc::setlocale(libc::LC_COLLATE, c"".as_ptr());
+        });
+
+        let c_name = match CString::new(name.as_bytes()) {
+            Ok(c_name) => c_name,
+            Err(_) => return name.as_bytes().to_vec(),
+        };
+        let len = unsafe { libc::strxfrm(std::ptr::null_mut(), c_name.as_ptr(), 0) };
+        let mut key = vec![0u8; len + 1];
+        unsafe {
+            libc::strxfrm(
+                key.as_mut_ptr() as *mut libc::c_char,
+                c_name.as_ptr(),
+                key.len(),
+            );
+        }
+        key.truncate(len);
+        key
+    }
+    #[cfg(not(unix))]
+    {
+        name.to_string_lossy().to_lowercase().into_bytes()
+    }
+}
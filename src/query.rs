@@ -0,0 +1,464 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::units;
+use crate::walk::Node;
+
+// A parsed `--query` expression, evaluated against every entry
+#[derive(Clone, Debug)]
+pub struct Query {
+    expr: Expr,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Name,
+    Ext,
+    Path,
+    Type,
+    Size,
+    Depth,
+    Age,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Glob,
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Str(String),
+    Num(u64),
+    Glob(GlobMatcher),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Query {
+    pub fn parse(source: &str) -> Result<Query, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {:?}", token));
+        }
+        Ok(Query { expr })
+    }
+
+    // The same query, with its globs matching regardless of case when
+    // `ignore_case` is set (--ignore-case)
+    pub fn with_case(&self, ignore_case: bool) -> Query {
+        Query {
+            expr: fold_globs(&self.expr, ignore_case),
+        }
+    }
+
+    pub fn matches(&self, node: &Node, root: &Path) -> bool {
+        eval(&self.expr, node, root)
+    }
+}
+
+fn glob(pattern: &str, ignore_case: bool) -> Result<GlobMatcher, String> {
+    GlobBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| e.kind().to_string())
+}
+
+fn fold_globs(expr: &Expr, ignore_case: bool) -> Expr {
+    let fold = |expr: &Expr| Box::new(fold_globs(expr, ignore_case));
+    match expr {
+        Expr::And(a, b) => Expr::And(fold(a), fold(b)),
+        Expr::Or(a, b) => Expr::Or(fold(a), fold(b)),
+        Expr::Not(a) => Expr::Not(fold(a)),
+        Expr::Compare(field, op, Value::Glob(pattern)) => {
+            // The pattern was checked when parsing
+            let source = pattern.glob().glob();
+            let pattern = glob(source, ignore_case).unwrap_or_else(|_| pattern.clone());
+            Expr::Compare(*field, *op, Value::Glob(pattern))
+        }
+        Expr::Compare(..) => expr.clone(),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '~' => Token::Op(Op::Glob),
+            '&' | '|' => {
+                chars.next();
+                if chars.peek() != Some(&c) {
+                    return Err(format!("expected '{}{}'", c, c));
+                }
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let eq = chars.peek() == Some(&'=');
+                if eq {
+                    chars.next();
+                }
+                let token = match (c, eq) {
+                    ('=', true) => Token::Op(Op::Eq),
+                    ('!', true) => Token::Op(Op::Ne),
+                    ('!', false) => Token::Not,
+                    ('<', true) => Token::Op(Op::Le),
+                    ('<', false) => Token::Op(Op::Lt),
+                    ('>', true) => Token::Op(Op::Ge),
+                    ('>', false) => Token::Op(Op::Gt),
+                    _ => return Err("expected '=='".to_string()),
+                };
+                tokens.push(token);
+                continue;
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(ch) => text.push(ch),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Str(text));
+                continue;
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "()~&|=!<>\"'".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+                continue;
+            }
+        };
+        chars.next();
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_unary()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat(&Token::Open) {
+            let expr = self.parse_or()?;
+            if !self.eat(&Token::Close) {
+                return Err("expected ')'".to_string());
+            }
+            return Ok(expr);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let name = match self.next() {
+            Some(Token::Word(word)) => word,
+            Some(token) => return Err(format!("expected a field, found {:?}", token)),
+            None => return Err("expected a field".to_string()),
+        };
+        let field = match name.as_str() {
+            "name" => Field::Name,
+            "ext" => Field::Ext,
+            "path" => Field::Path,
+            "type" => Field::Type,
+            "size" => Field::Size,
+            "depth" => Field::Depth,
+            "age" => Field::Age,
+            _ => return Err(format!("unknown field '{}'", name)),
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => return Err(format!("expected an operator after '{}'", name)),
+        };
+        let literal = match self.next() {
+            Some(Token::Word(word)) | Some(Token::Str(word)) => word,
+            _ => return Err(format!("expected a value after '{}'", name)),
+        };
+
+        let value = match field {
+            Field::Size | Field::Depth | Field::Age => {
+                if op == Op::Glob {
+                    return Err(format!("'~' can't be used with '{}'", name));
+                }
+                Value::Num(match field {
+                    Field::Size => units::parse_size(&literal)?,
+                    Field::Age => units::parse_duration(&literal)?,
+                    _ => literal
+                        .parse()
+                        .map_err(|_| format!("invalid depth '{}'", literal))?,
+                })
+            }
+            _ => match op {
+                Op::Glob => Value::Glob(glob(&literal, false)?),
+                Op::Eq | Op::Ne => {
+                    if field == Field::Type && !["file", "dir", "link"].contains(&&*literal) {
+                        return Err(format!("unknown type '{}'", literal));
+                    }
+                    Value::Str(literal)
+                }
+                _ => return Err(format!("'{}' only supports ==, != and ~", name)),
+            },
+        };
+
+        Ok(Expr::Compare(field, op, value))
+    }
+}
+
+fn eval(expr: &Expr, node: &Node, root: &Path) -> bool {
+    match expr {
+        Expr::And(a, b) => eval(a, node, root) && eval(b, node, root),
+        Expr::Or(a, b) => eval(a, node, root) || eval(b, node, root),
+        Expr::Not(a) => !eval(a, node, root),
+        Expr::Compare(field, op, value) => match value {
+            Value::Num(expected) => {
+                let actual = match field {
+                    // With --du, the total shown for a directory
+                    Field::Size => node.size(),
+                    Field::Depth => node.depth as u64,
+                    _ => node
+                        .metadata
+                        .modified()
+                        .ok()
+                        .and_then(|mtime| SystemTime::now().duration_since(mtime).ok())
+                        .map(|age| age.as_secs())
+                        .unwrap_or(0),
+                };
+                match op {
+                    Op::Eq => actual == *expected,
+                    Op::Ne => actual != *expected,
+                    Op::Lt => actual < *expected,
+                    Op::Le => actual <= *expected,
+                    Op::Gt => actual > *expected,
+                    Op::Ge => actual >= *expected,
+                    Op::Glob => false,
+                }
+            }
+            Value::Str(expected) => {
+                (text_field(*field, node, root) == *expected) == (*op == Op::Eq)
+            }
+            Value::Glob(pattern) => pattern.is_match(text_field(*field, node, root)),
+        },
+    }
+}
+
+fn text_field(field: Field, node: &Node, root: &Path) -> String {
+    match field {
+        Field::Name => node.name.clone(),
+        Field::Ext => match node.name.rfind('.') {
+            Some(idx) if idx > 0 => node.name[idx + 1..].to_string(),
+            _ => String::new(),
+        },
+        Field::Path => node
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&node.path)
+            .to_string_lossy()
+            .into_owned(),
+        _ => {
+            if node.metadata.file_type().is_symlink() {
+                "link".to_string()
+            } else if node.is_dir() {
+                "dir".to_string()
+            } else {
+                "file".to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // The expression in prefix form, fully parenthesized
+    fn show(expr: &Expr) -> String {
+        match expr {
+            Expr::And(a, b) => format!("(and {} {})", show(a), show(b)),
+            Expr::Or(a, b) => format!("(or {} {})", show(a), show(b)),
+            Expr::Not(a) => format!("(not {})", show(a)),
+            Expr::Compare(field, op, value) => {
+                let value = match value {
+                    Value::Str(s) => format!("{:?}", s),
+                    Value::Num(n) => n.to_string(),
+                    Value::Glob(pattern) => format!("~{:?}", pattern.glob().glob()),
+                };
+                format!("({:?} {:?} {})", op, field, value)
+            }
+        }
+    }
+
+    fn parse(source: &str) -> String {
+        show(&Query::parse(source).unwrap().expr)
+    }
+
+    #[test]
+    fn tokenizes() {
+        assert_eq!(
+            tokenize("size>=10M&&!(name~'*.rs')").unwrap(),
+            [
+                Token::Word("size".into()),
+                Token::Op(Op::Ge),
+                Token::Word("10M".into()),
+                Token::And,
+                Token::Not,
+                Token::Open,
+                Token::Word("name".into()),
+                Token::Op(Op::Glob),
+                Token::Str("*.rs".into()),
+                Token::Close,
+            ]
+        );
+        assert!(tokenize("a & b").is_err());
+        assert!(tokenize("a = b").is_err());
+        assert!(tokenize("name == \"open").is_err());
+    }
+
+    #[test]
+    fn parses_precedence() {
+        assert_eq!(
+            parse("depth <= 3 || ext == \"log\" && size > 10M"),
+            "(or (Le Depth 3) (and (Eq Ext \"log\") (Gt Size 10485760)))"
+        );
+        assert_eq!(
+            parse("(depth <= 3 || ext == log) && !type == dir"),
+            "(and (or (Le Depth 3) (Eq Ext \"log\")) (not (Eq Type \"dir\")))"
+        );
+        assert_eq!(parse("age < 2d"), "(Lt Age 172800)");
+        assert_eq!(parse("path ~ 'src/**'"), "(Glob Path ~\"src/**\")");
+    }
+
+    #[test]
+    fn rejects_invalid_queries() {
+        for source in [
+            "",
+            "size >",
+            "colour == red",
+            "size ~ 10M",
+            "name < b",
+            "type == socket",
+            "depth == deep",
+            "(size > 1",
+            "size > 1 size < 2",
+        ] {
+            assert!(Query::parse(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn evaluates() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let node = Node {
+            metadata: fs::metadata(&path).unwrap(),
+            name: "Cargo.toml".to_string(),
+            path,
+            depth: 1,
+            children: Vec::new(),
+            omitted: 0,
+            followed: false,
+            badge: None,
+            rollup: None,
+            error: None,
+            du: None,
+            lines: None,
+        };
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let matches = |source: &str| Query::parse(source).unwrap().matches(&node, root);
+        assert!(matches("ext == toml && type == file && depth == 1"));
+        assert!(matches("name ~ 'Cargo.*' && size > 0"));
+        assert!(matches("!(type == dir) || size > 1T"));
+        assert!(!matches("ext != toml"));
+        assert!(!matches("path ~ 'src/*'"));
+
+        let query = Query::parse("name ~ 'cargo.*'").unwrap();
+        assert!(!query.matches(&node, root));
+        assert!(query.with_case(true).matches(&node, root));
+    }
+}
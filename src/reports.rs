@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::i18n::{self, Msg};
+use crate::walk::Node;
+use crate::{fsinfo, names, platform, units};
+
+// Count the inodes used below each top-level entry of `root`, biggest
+// first. Everything on the root's filesystem counts, hidden and ignored
+// entries included, and hard links are only counted once.
+pub fn inode_usage(root: &Path, escape: bool, out: &mut dyn Write) -> io::Result<()> {
+    let root_dev = platform::file_id(root, &fs::metadata(root)?).0;
+    let mut seen = HashSet::new();
+
+    let mut rows: Vec<(String, usize)> = Vec::new();
+    for entry in fs::read_dir(root)?.filter_map(Result::ok) {
+        let mut count = 0;
+        count_inodes(&entry.path(), root_dev, &mut seen, &mut count);
+        rows.push((names::display(&entry.file_name(), escape), count));
+    }
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let fs_inodes = fsinfo::usage(root).map(|u| u.inodes).unwrap_or(0);
+    let total: usize = rows.iter().map(|(_, count)| count).sum();
+    let total_label = i18n::tr(Msg::Total);
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .chain([total_label])
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let percent = |count: usize| match fs_inodes {
+        0 => String::new(),
+        inodes => format!(
+            "{:>7}%",
+            units::decimal(count as f64 * 100.0 / inodes as f64, 2)
+        ),
+    };
+
+    writeln!(out, "\n{}", i18n::tr(Msg::InodeUsage))?;
+    for (name, count) in &rows {
+        writeln!(
+            out,
+            "  {:<width$}  {:>12}  {}",
+            name,
+            units::group_digits(*count),
+            percent(*count),
+            width = width
+        )?;
+    }
+    writeln!(
+        out,
+        "  {:<width$}  {:>12}  {}",
+        total_label,
+        units::group_digits(total),
+        percent(total),
+        width = width
+    )?;
+    if fs_inodes > 0 {
+        writeln!(
+            out,
+            "  {}",
+            i18n::fill(Msg::OfInodes, &[&units::group_digits(fs_inodes as usize)])
+        )?;
+    }
+
+    Ok(())
+}
+
+fn count_inodes(path: &Path, dev: u64, seen: &mut HashSet<u64>, count: &mut usize) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(_) => return,
+    };
+    // Other filesystems have inodes of their own
+    let (file_dev, inode) = platform::file_id(path, &metadata);
+    if file_dev != dev || !seen.insert(inode) {
+        return;
+    }
+    *count += 1;
+
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.filter_map(Result::ok) {
+                count_inodes(&entry.path(), dev, seen, count);
+            }
+        }
+    }
+}
+
+// Report the least and most recently modified files of the tree
+pub fn time_range(nodes: &[Node], escape: bool, out: &mut dyn Write) -> io::Result<()> {
+    let mut oldest: Option<(SystemTime, &Node)> = None;
+    let mut newest: Option<(SystemTime, &Node)> = None;
+    visit_files(nodes, &mut |node| {
+        if let Ok(mtime) = node.metadata.modified() {
+            if oldest.is_none_or(|(time, _)| mtime < time) {
+                oldest = Some((mtime, node));
+            }
+            if newest.is_none_or(|(time, _)| mtime > time) {
+                newest = Some((mtime, node));
+            }
+        }
+    });
+
+    if let (Some((old_time, old)), Some((new_time, new))) = (oldest, newest) {
+        let [oldest, newest] = i18n::padded([Msg::Oldest, Msg::Newest]);
+        writeln!(
+            out,
+            "\n{} {}  {}",
+            oldest,
+            units::format_time(old_time),
+            shown(&old.path, escape)
+        )?;
+        writeln!(
+            out,
+            "{} {}  {}",
+            newest,
+            units::format_time(new_time),
+            shown(&new.path, escape)
+        )?;
+    }
+
+    Ok(())
+}
+
+// Call `f` for every non-directory entry of the tree
+fn visit_files<'a>(nodes: &'a [Node], f: &mut impl FnMut(&'a Node)) {
+    for node in nodes {
+        if !node.is_dir() && !node.followed {
+            f(node);
+        }
+        visit_files(&node.children, f);
+    }
+}
+
+#[derive(Default)]
+struct Shape<'a> {
+    entries: usize,
+    depth_sum: usize,
+    max_depth: usize,
+    widest: Option<(&'a Path, usize)>,
+    longest_path: Option<&'a Path>,
+    longest_name: Option<&'a str>,
+}
+
+// Report the depth, width and name lengths of the tree, for spotting
+// hierarchies that trip up other tools
+pub fn shape_stats(
+    root: &Path,
+    nodes: &[Node],
+    escape: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let mut shape = Shape {
+        widest: Some((root, nodes.len())),
+        ..Shape::default()
+    };
+    measure(nodes, &mut shape);
+
+    let chars = |s: &str| s.chars().count();
+    let average = match shape.entries {
+        0 => 0.0,
+        entries => shape.depth_sum as f64 / entries as f64,
+    };
+
+    let mut rows = vec![
+        (Msg::MaxDepth, shape.max_depth.to_string()),
+        (Msg::AverageDepth, units::decimal(average, 2)),
+    ];
+    if let Some((path, width)) = shape.widest {
+        let entries = i18n::fill(Msg::EntryCount, &[&units::group_digits(width)]);
+        rows.push((
+            Msg::WidestDir,
+            format!("{} {}", shown(path, escape), entries),
+        ));
+    }
+    if let Some(path) = shape.longest_path {
+        let path = shown(path, escape);
+        let length = i18n::fill(Msg::CharCount, &[&chars(&path)]);
+        rows.push((Msg::LongestPath, format!("{} {}", path, length)));
+    }
+    if let Some(name) = shape.longest_name {
+        let length = i18n::fill(Msg::CharCount, &[&chars(name)]);
+        rows.push((Msg::LongestName, format!("{} {}", name, length)));
+    }
+
+    let rows: Vec<(&str, String)> = rows
+        .into_iter()
+        .map(|(label, value)| (i18n::tr(label), value))
+        .collect();
+    let width = rows
+        .iter()
+        .map(|(label, _)| chars(label))
+        .max()
+        .unwrap_or(0);
+    writeln!(out, "\n{}", i18n::tr(Msg::Shape))?;
+    for (label, value) in rows {
+        writeln!(out, "  {:<width$}  {}", label, value)?;
+    }
+
+    Ok(())
+}
+
+fn measure<'a>(nodes: &'a [Node], shape: &mut Shape<'a>) {
+    for node in nodes {
+        shape.entries += 1;
+        shape.depth_sum += node.depth;
+        shape.max_depth = shape.max_depth.max(node.depth);
+
+        let path_len = node.path.as_os_str().len();
+        if shape
+            .longest_path
+            .is_none_or(|longest| path_len > longest.as_os_str().len())
+        {
+            shape.longest_path = Some(&node.path);
+        }
+        if shape
+            .longest_name
+            .is_none_or(|longest| node.name.len() > longest.len())
+        {
+            shape.longest_name = Some(&node.name);
+        }
+        if !node.children.is_empty()
+            && shape
+                .widest
+                .is_none_or(|(_, width)| node.children.len() > width)
+        {
+            shape.widest = Some((&node.path, node.children.len()));
+        }
+
+        measure(&node.children, shape);
+    }
+}
+
+// Names Windows refuses regardless of extension
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// List entries whose names tend to break scripts or other platforms.
+// Their paths are always escaped, to show what's wrong with them.
+pub fn problem_names(nodes: &[Node], out: &mut dyn Write) -> io::Result<()> {
+    let mut problems = Vec::new();
+    collect_problems(nodes, &mut problems);
+
+    if problems.is_empty() {
+        writeln!(out, "\n{}", i18n::tr(Msg::NoProblemNames))?;
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", i18n::tr(Msg::ProblemNames))?;
+    for (node, issues) in problems {
+        writeln!(
+            out,
+            "  {}  ({})",
+            shown(&node.path, true),
+            issues.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+fn collect_problems<'a>(nodes: &'a [Node], problems: &mut Vec<(&'a Node, Vec<String>)>) {
+    for node in nodes {
+        let issues = name_issues(node);
+        if !issues.is_empty() {
+            problems.push((node, issues));
+        }
+        collect_problems(&node.children, problems);
+    }
+}
+
+fn name_issues(node: &Node) -> Vec<String> {
+    // The name as stored, not as printed with -b
+    let raw = node.path.file_name().unwrap_or(node.path.as_os_str());
+    let name = raw.to_string_lossy();
+    let mut issues = Vec::new();
+
+    if raw.to_str().is_none() {
+        issues.push(i18n::tr(Msg::NotUtf8).to_string());
+    }
+    if name.starts_with(char::is_whitespace) || name.ends_with(char::is_whitespace) {
+        issues.push(i18n::tr(Msg::EdgeWhitespace).to_string());
+    } else if name.contains(' ') {
+        issues.push(i18n::tr(Msg::Space).to_string());
+    }
+    if name.chars().any(char::is_control) {
+        issues.push(i18n::tr(Msg::ControlChar).to_string());
+    }
+
+    let invalid: String = name.chars().filter(|c| "<>:\"|?*\\".contains(*c)).collect();
+    if !invalid.is_empty() {
+        issues.push(i18n::fill(Msg::InvalidOnWindows, &[&invalid]));
+    }
+    let stem = name.split('.').next().unwrap_or("").to_ascii_uppercase();
+    if WINDOWS_RESERVED.contains(&stem.as_str()) {
+        issues.push(i18n::tr(Msg::ReservedOnWindows).to_string());
+    }
+    if name.ends_with('.') && name != "." && name != ".." {
+        issues.push(i18n::tr(Msg::TrailingDot).to_string());
+    }
+
+    issues
+}
+
+// List file names found in more than one directory, most frequent first
+pub fn name_clashes(nodes: &[Node], escape: bool, out: &mut dyn Write) -> io::Result<()> {
+    let mut by_name: HashMap<&OsStr, Vec<&Path>> = HashMap::new();
+    visit_files(nodes, &mut |node| {
+        let name = node.path.file_name().unwrap_or(node.path.as_os_str());
+        by_name.entry(name).or_default().push(&node.path);
+    });
+
+    let mut clashes: Vec<_> = by_name
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+    clashes.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+
+    if clashes.is_empty() {
+        writeln!(out, "\n{}", i18n::tr(Msg::NoNameClashes))?;
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", i18n::tr(Msg::NameClashes))?;
+    for (name, paths) in clashes {
+        writeln!(out, "  {} ({})", names::display(name, escape), paths.len())?;
+        for path in paths {
+            writeln!(out, "    {}", shown(path, escape))?;
+        }
+    }
+    Ok(())
+}
+
+// `path` for printing, names that aren't UTF-8 included
+fn shown(path: &Path, escape: bool) -> String {
+    names::display(platform::plain_path(path).as_os_str(), escape)
+}
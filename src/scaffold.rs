@@ -0,0 +1,298 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::i18n::{self, Msg};
+
+// Tokens that make up one indentation level of tree output
+const TREE_TOKENS: [&str; 7] = ["│   ", "├── ", "└── ", "|   ", "|-- ", "`-- ", "    "];
+
+struct LayoutEntry {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+// Create every directory and file described by `layout` below `dest`
+pub fn apply(layout: &Path, dest: &Path, dry_run: bool) -> io::Result<()> {
+    let text = fs::read_to_string(layout)?;
+    let entries = parse_layout(&text)?;
+
+    let [would_create, exists, created] =
+        i18n::padded([Msg::WouldCreate, Msg::Exists, Msg::Created]);
+    let [dir, file] = i18n::padded([Msg::Dir, Msg::File]);
+
+    for entry in entries {
+        let target = dest.join(&entry.path);
+        let kind = if entry.is_dir { &dir } else { &file };
+
+        if dry_run {
+            println!("{} {} {}", would_create, kind, target.display());
+            continue;
+        }
+
+        if entry.is_dir {
+            if target.is_dir() {
+                println!("{} {} {}", exists, kind, target.display());
+                continue;
+            }
+            fs::create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Never clobber files that already exist
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&target)
+            {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    println!("{} {} {}", exists, kind, target.display());
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        println!("{} {} {}", created, kind, target.display());
+    }
+
+    Ok(())
+}
+
+// Parse tree output or a plain indented list into relative paths.
+// Nesting is derived from the column each name starts at, so any
+// consistent indentation works. Names with children or a trailing '/'
+// are directories, everything else becomes an empty file.
+fn parse_layout(text: &str) -> io::Result<Vec<LayoutEntry>> {
+    let mut entries: Vec<LayoutEntry> = Vec::new();
+    // (column, index into entries) of the current ancestors
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+
+    for (lineno, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || is_report_line(line) {
+            continue;
+        }
+
+        let (column, rest) = split_indent(line);
+        let mut name = strip_comment(rest).trim_end();
+
+        if name.contains(" -> ") {
+            eprintln!(
+                "rtree: {}",
+                i18n::fill(Msg::SkippingSymlink, &[&(lineno + 1), &name])
+            );
+            continue;
+        }
+
+        let mut is_dir = false;
+        if name.len() > 1 && name.ends_with('/') {
+            name = name.trim_end_matches('/');
+            is_dir = true;
+        }
+
+        let mut relative = Path::new(name);
+        // The root line of tree output is often an absolute path
+        if stack.is_empty() && relative.is_absolute() {
+            if let Some(base) = relative.file_name() {
+                relative = Path::new(base);
+            }
+        }
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                i18n::fill(Msg::OutsideDestination, &[&(lineno + 1), &name]),
+            ));
+        }
+
+        while let Some(&(col, _)) = stack.last() {
+            if col >= column {
+                stack.pop();
+            } else {
+                break;
+            }
+        }
+
+        let path = match stack.last() {
+            Some(&(_, parent)) => {
+                // Having a child makes the parent a directory
+                entries[parent].is_dir = true;
+                entries[parent].path.join(relative)
+            }
+            None => relative.to_path_buf(),
+        };
+
+        stack.push((column, entries.len()));
+        entries.push(LayoutEntry { path, is_dir });
+    }
+
+    Ok(entries)
+}
+
+// Returns the display column of the name and the remainder of the line
+fn split_indent(line: &str) -> (usize, &str) {
+    let mut rest = line;
+    let mut column = 0;
+
+    'outer: loop {
+        for token in TREE_TOKENS {
+            if let Some(stripped) = rest.strip_prefix(token) {
+                rest = stripped;
+                column += 4;
+                continue 'outer;
+            }
+        }
+        if let Some(stripped) = rest.strip_prefix('\t') {
+            rest = stripped;
+            column += 4;
+        } else if let Some(stripped) = rest.strip_prefix(' ') {
+            rest = stripped;
+            column += 1;
+        } else {
+            break;
+        }
+    }
+
+    (column, rest)
+}
+
+// Layout docs often annotate entries like `src/  # application code`
+fn strip_comment(name: &str) -> &str {
+    match name.find("  #") {
+        Some(idx) => &name[..idx],
+        None => name,
+    }
+}
+
+// Skip the "N directories, M files" and timing lines of pasted output,
+// in any of the languages rtree speaks, and GNU tree's English ones
+fn is_report_line(line: &str) -> bool {
+    let line = line.trim();
+    let gnu_tree = (line.ends_with(" files") || line.ends_with(" file"))
+        && line.contains(" director")
+        && line.starts_with(|c: char| c.is_ascii_digit());
+    gnu_tree
+        || [Msg::Summary, Msg::UsedSummary, Msg::TimeTaken]
+            .into_iter()
+            .flat_map(i18n::translations)
+            .any(|template| fills(template, line))
+}
+
+// Whether `line` starts with `template` filled in, each `{}` standing
+// for a number (a count, a size or a duration)
+fn fills(template: &str, line: &str) -> bool {
+    let mut parts = template.split("{}");
+    let mut rest = match line.strip_prefix(parts.next().unwrap_or("")) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    for part in parts {
+        if !rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+        rest = match rest.find(part) {
+            Some(end) => &rest[end + part.len()..],
+            None => return false,
+        };
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(text: &str) -> Vec<(String, bool)> {
+        parse_layout(text)
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.path.to_string_lossy().replace('\\', "/"),
+                    entry.is_dir,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_tree_output() {
+        let text = "/home/me/project\n\
+                    ├── src\n\
+                    │   ├── main.rs\n\
+                    │   └── lib.rs\n\
+                    └── README.md\n\
+                    \n\
+                    1 directories, 3 files\n";
+        assert_eq!(
+            paths(text),
+            [
+                ("project".to_string(), true),
+                ("project/src".to_string(), true),
+                ("project/src/main.rs".to_string(), false),
+                ("project/src/lib.rs".to_string(), false),
+                ("project/README.md".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_report_lines_in_any_language() {
+        let text = "projekt\n\
+                    └── src\n\
+                    \n\
+                    1 Verzeichnisse, 0 Dateien\n\
+                    Benötigte Zeit: 1.2ms\n\
+                    4,0 KiB utilisés dans 1 répertoires, 0 fichiers, 2 erreurs\n\
+                    1 directory, 1 file\n";
+        assert_eq!(
+            paths(text),
+            [
+                ("projekt".to_string(), true),
+                ("projekt/src".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_ascii_tree_output() {
+        let text = "app\n|-- bin\n|   `-- run\n`-- Makefile\n";
+        assert_eq!(
+            paths(text),
+            [
+                ("app".to_string(), true),
+                ("app/bin".to_string(), true),
+                ("app/bin/run".to_string(), false),
+                ("app/Makefile".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_indented_lists() {
+        let text = "docs/\n  guide.md  # the manual\nempty/\nnotes.txt\n";
+        assert_eq!(
+            paths(text),
+            [
+                ("docs".to_string(), true),
+                ("docs/guide.md".to_string(), false),
+                ("empty".to_string(), true),
+                ("notes.txt".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_symlinks() {
+        assert_eq!(paths("a\n└── link -> target\n"), [("a".to_string(), false)]);
+    }
+
+    #[test]
+    fn rejects_paths_leaving_the_destination() {
+        assert!(parse_layout("a\n└── ../escape\n").is_err());
+        assert!(parse_layout("src\n    /etc/passwd\n").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use clap::ValueEnum;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::fs;
+use std::time::SystemTime;
+
+use crate::walk::Node;
+use crate::{names, platform, Opt};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum SortKey {
+    Name,
+    /// Largest first, directories by their total with --du
+    Size,
+    /// Last modified first
+    Mtime,
+    /// By name, with runs of digits compared as numbers (file2 before file10)
+    Version,
+    /// By name, in the collation order of the locale (LC_COLLATE)
+    Locale,
+    /// The order the directory lists them in
+    None,
+}
+
+// What entries are compared by, taken from a DirEntry before the walk
+// or from a Node after it
+struct Key<'a> {
+    name: Cow<'a, OsStr>,
+    size: u64,
+    modified: SystemTime,
+    // Directories, and links --follow descends into
+    is_dir: bool,
+    // The name as the locale orders it (with --sort=locale)
+    collated: Vec<u8>,
+}
+
+impl Key<'_> {
+    fn of_entry(entry: &fs::DirEntry, opt: &Opt) -> Key<'static> {
+        // Only sorts that need it pay for the stat
+        let metadata = match key(opt) {
+            SortKey::Size | SortKey::Mtime => entry.metadata().ok(),
+            _ => None,
+        };
+        let is_dir = grouped(opt)
+            && match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => opt.follow && entry.path().is_dir(),
+                Ok(file_type) => file_type.is_dir(),
+                Err(_) => false,
+            };
+        let name = entry.file_name();
+        Key {
+            collated: collated(&name, opt),
+            name: Cow::Owned(name),
+            size: metadata.as_ref().map_or(0, |metadata| metadata.len()),
+            modified: modified(metadata.as_ref()),
+            is_dir,
+        }
+    }
+
+    fn of_node<'a>(node: &'a Node, opt: &Opt) -> Key<'a> {
+        let name = node.path.file_name().unwrap_or_default();
+        Key {
+            name: Cow::Borrowed(name),
+            size: node.size(),
+            modified: modified(Some(&node.metadata)),
+            is_dir: node.is_dir() || node.followed,
+            collated: collated(name, opt),
+        }
+    }
+}
+
+// The collation key of `name` for --sort=locale. --no-config keeps out
+// the locale, leaving byte order.
+fn collated(name: &OsStr, opt: &Opt) -> Vec<u8> {
+    match key(opt) {
+        SortKey::Locale if opt.no_config => names::os_bytes(name).into_owned(),
+        SortKey::Locale => platform::collation_key(name),
+        _ => Vec::new(),
+    }
+}
+
+// Entries without a modification time sort as the oldest
+fn modified(metadata: Option<&fs::Metadata>) -> SystemTime {
+    metadata
+        .and_then(|metadata| metadata.modified().ok())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+// The sort in effect, with -t standing for --sort=mtime, -v for
+// --sort=version and -U for --sort=none
+pub fn key(opt: &Opt) -> SortKey {
+    if opt.unsorted {
+        SortKey::None
+    } else if opt.sort_mtime {
+        SortKey::Mtime
+    } else if opt.sort_version {
+        SortKey::Version
+    } else {
+        opt.sort
+    }
+}
+
+// Whether directories and files are kept apart (--dirsfirst, --filesfirst).
+// -U leaves the entries exactly as read, as with GNU tree.
+fn grouped(opt: &Opt) -> bool {
+    (opt.dirsfirst || opt.filesfirst) && !opt.unsorted
+}
+
+// The order of `a` and `b`, turned around by -r. Directories stay
+// ahead of files (or behind them) either way.
+fn compare(a: &Key, b: &Key, opt: &Opt) -> Ordering {
+    let group = if opt.dirsfirst {
+        b.is_dir.cmp(&a.is_dir)
+    } else if opt.filesfirst {
+        a.is_dir.cmp(&b.is_dir)
+    } else {
+        Ordering::Equal
+    };
+    let order = match key(opt) {
+        SortKey::Name => a.name.cmp(&b.name),
+        SortKey::Size => b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)),
+        SortKey::Mtime => b
+            .modified
+            .cmp(&a.modified)
+            .then_with(|| a.name.cmp(&b.name)),
+        SortKey::Version => version_cmp(&names::os_bytes(&a.name), &names::os_bytes(&b.name))
+            .then_with(|| a.name.cmp(&b.name)),
+        SortKey::Locale => a
+            .collated
+            .cmp(&b.collated)
+            .then_with(|| a.name.cmp(&b.name)),
+        SortKey::None => Ordering::Equal,
+    };
+    group.then(if opt.reverse { order.reverse() } else { order })
+}
+
+// Compare names as `sort -V` does: runs of digits by their value, the
+// rest byte by byte, so v1.9.0 comes before v1.10.0. Leading zeros only
+// break ties.
+fn version_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let a_end = digits_end(a, i);
+            let b_end = digits_end(b, j);
+            let a_number = trim_zeros(&a[i..a_end]);
+            let b_number = trim_zeros(&b[j..b_end]);
+            // Without leading zeros the longer run is the bigger number
+            let order = a_number
+                .len()
+                .cmp(&b_number.len())
+                .then_with(|| a_number.cmp(b_number));
+            if order != Ordering::Equal {
+                return order;
+            }
+            i = a_end;
+            j = b_end;
+        } else {
+            if a[i] != b[j] {
+                return a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j))
+}
+
+fn digits_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .map_or(bytes.len(), |n| start + n)
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&b| b == b'0').count();
+    // Keep one digit of an all-zero run
+    &digits[zeros.min(digits.len() - 1)..]
+}
+
+// Put the entries of a directory in the order asked for by --sort
+pub fn sort_entries(entries: &mut Vec<fs::DirEntry>, opt: &Opt) {
+    if key(opt) == SortKey::None {
+        if opt.reverse {
+            entries.reverse();
+        }
+        if !grouped(opt) {
+            return;
+        }
+    }
+    let mut keyed: Vec<(Key, fs::DirEntry)> = entries
+        .drain(..)
+        .map(|entry| (Key::of_entry(&entry, opt), entry))
+        .collect();
+    keyed.sort_by(|a, b| compare(&a.0, &b.0, opt));
+    entries.extend(keyed.into_iter().map(|(_, entry)| entry));
+}
+
+// Sort the walked entries again where their order depends on what's
+// below them: directories are only sized by --du once they're walked
+pub fn sort_nodes(nodes: &mut [Node], opt: &Opt) {
+    if key(opt) == SortKey::Size && opt.du {
+        nodes.sort_by(|a, b| compare(&Key::of_node(a, opt), &Key::of_node(b, opt), opt));
+    }
+}
+
+// The order of two walked entries, as the walk would have put them
+pub fn compare_nodes(a: &Node, b: &Node, opt: &Opt) -> Ordering {
+    compare(&Key::of_node(a, opt), &Key::of_node(b, opt), opt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names = names.to_vec();
+        names.sort_by(|a, b| version_cmp(a.as_bytes(), b.as_bytes()).then_with(|| a.cmp(b)));
+        names.into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn compares_numbers_by_value() {
+        assert_eq!(version_cmp(b"file2", b"file10"), Ordering::Less);
+        assert_eq!(version_cmp(b"v1.10.0", b"v1.9.0"), Ordering::Greater);
+        assert_eq!(version_cmp(b"a100b", b"a100c"), Ordering::Less);
+        assert_eq!(version_cmp(b"x", b"x1"), Ordering::Less);
+        assert_eq!(version_cmp(b"10", b"9a"), Ordering::Greater);
+        assert_eq!(version_cmp(b"file01", b"file1"), Ordering::Equal);
+        assert_eq!(version_cmp(b"000", b"0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn sorts_like_sort_v() {
+        assert_eq!(
+            sorted(&[
+                "v1.10.0",
+                "file1",
+                "v1.9.0",
+                "file10",
+                "file01",
+                "file2",
+                "v1.9.0-rc1"
+            ]),
+            [
+                "file01",
+                "file1",
+                "file2",
+                "file10",
+                "v1.9.0",
+                "v1.9.0-rc1",
+                "v1.10.0"
+            ]
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+
+// Named NTFS alternate data streams attached to `path`, with their sizes.
+// The unnamed default stream holding the file contents is left out.
+#[cfg(windows)]
+pub fn alternate_streams(path: &Path) -> Vec<(String, u64)> {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStrExt;
+
+    const MAX_PATH: usize = 260;
+    const INVALID_HANDLE_VALUE: isize = -1;
+    const FIND_STREAM_INFO_STANDARD: i32 = 0;
+
+    #[repr(C)]
+    struct FindStreamData {
+        stream_size: i64,
+        stream_name: [u16; MAX_PATH + 36],
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn FindFirstStreamW(
+            file_name: *const u16,
+            info_level: i32,
+            find_stream_data: *mut c_void,
+            flags: u32,
+        ) -> isize;
+        fn FindNextStreamW(find_stream: isize, find_stream_data: *mut c_void) -> i32;
+        fn FindClose(find_file: isize) -> i32;
+    }
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut data = FindStreamData {
+        stream_size: 0,
+        stream_name: [0; MAX_PATH + 36],
+    };
+    let data_ptr = &mut data as *mut FindStreamData as *mut c_void;
+
+    let handle = unsafe { FindFirstStreamW(wide.as_ptr(), FIND_STREAM_INFO_STANDARD, data_ptr, 0) };
+    if handle == INVALID_HANDLE_VALUE {
+        return Vec::new();
+    }
+
+    let mut streams = Vec::new();
+    loop {
+        let len = data
+            .stream_name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(data.stream_name.len());
+        let name = String::from_utf16_lossy(&data.stream_name[..len]);
+        // Names come as ":name:$DATA", the default stream is "::$DATA"
+        let name = name.strip_suffix(":$DATA").unwrap_or(&name);
+        if !name.is_empty() && name != ":" {
+            streams.push((name.to_string(), data.stream_size.max(0) as u64));
+        }
+
+        if unsafe { FindNextStreamW(handle, data_ptr) } == 0 {
+            break;
+        }
+    }
+    unsafe { FindClose(handle) };
+
+    streams
+}
+
+// Only NTFS has alternate data streams
+#[cfg(not(windows))]
+pub fn alternate_streams(_path: &Path) -> Vec<(String, u64)> {
+    Vec::new()
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Local, SecondsFormat};
+use std::io::{self, Write};
+
+use crate::walk::Node;
+use crate::{json, names};
+
+// Write one row per entry with its path, depth, type, size and
+// modification time. Values stay in machine formats (bytes, RFC 3339)
+// whatever the locale.
+pub fn write_rows(nodes: &[Node], separator: char, out: &mut dyn Write) -> io::Result<()> {
+    let header = ["path", "depth", "type", "size", "mtime"];
+    writeln!(out, "{}", header.join(&separator.to_string()))?;
+    write_nodes(nodes, separator, out)
+}
+
+fn write_nodes(nodes: &[Node], separator: char, out: &mut dyn Write) -> io::Result<()> {
+    for node in nodes {
+        let kind = json::kind(node);
+        let mtime = node
+            .metadata
+            .modified()
+            .map(|time| DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false))
+            .unwrap_or_default();
+
+        let fields = [
+            names::display(node.path.as_os_str(), false),
+            node.depth.to_string(),
+            kind.to_string(),
+            node.metadata.len().to_string(),
+            mtime,
+        ];
+        let row: Vec<String> = fields.iter().map(|f| field(f, separator)).collect();
+        writeln!(out, "{}", row.join(&separator.to_string()))?;
+
+        write_nodes(&node.children, separator, out)?;
+    }
+    Ok(())
+}
+
+// CSV quotes fields that need it (RFC 4180), TSV has no quoting so tabs,
+// newlines and backslashes are escaped instead
+fn field(value: &str, separator: char) -> String {
+    if separator == '\t' {
+        return value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+    }
+    if value.contains([separator, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,263 @@
+use colored::{Color, Colorize};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use toml::Spanned;
+
+use crate::config::line_of;
+
+// How to draw one kind of entry, e.g. "bold blue" or "italic #ff8800"
+#[derive(Clone, Default)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    dimmed: bool,
+}
+
+impl Style {
+    fn fg(color: Color) -> Style {
+        Style {
+            fg: Some(color),
+            ..Style::default()
+        }
+    }
+
+    pub fn paint(&self, text: &str) -> String {
+        let mut painted = text.normal();
+        if let Some(fg) = self.fg {
+            painted = painted.color(fg);
+        }
+        if let Some(bg) = self.bg {
+            painted = painted.on_color(bg);
+        }
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.italic {
+            painted = painted.italic();
+        }
+        if self.underline {
+            painted = painted.underline();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        painted.to_string()
+    }
+
+    // Words: bold, italic, underline, dimmed, a color name ("red",
+    // "bright red") or #rrggbb, and `on COLOR` for the background
+    fn parse(spec: &str) -> Result<Style, String> {
+        let mut style = Style::default();
+        let mut words = spec.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            match word {
+                "bold" => style.bold = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                "dimmed" | "dim" => style.dimmed = true,
+                "normal" | "plain" => {}
+                "on" => {
+                    let color = words.next().ok_or("missing color after 'on'")?;
+                    style.bg = Some(parse_color(color, &mut words)?);
+                }
+                _ => style.fg = Some(parse_color(word, &mut words)?),
+            }
+        }
+        Ok(style)
+    }
+}
+
+fn parse_color<'a>(
+    word: &'a str,
+    words: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+) -> Result<Color, String> {
+    if let Some(hex) = word.strip_prefix('#') {
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or(""), 16);
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Ok(r), Ok(g), Ok(b)) => Ok(Color::TrueColor { r, g, b }),
+            _ => Err(format!("invalid color '{}'", word)),
+        };
+    }
+    let name = match word {
+        "bright" => format!(
+            "bright {}",
+            words.next().ok_or("missing color after 'bright'")?
+        ),
+        _ => word.replace(['-', '_'], " "),
+    };
+    name.parse()
+        .map_err(|_| format!("unknown color or style '{}'", name))
+}
+
+// Styles for each kind of entry, used when LS_COLORS doesn't apply
+pub struct Theme {
+    pub directory: Style,
+    pub symlink: Style,
+    pub link_target: Style,
+    pub executable: Style,
+    pub file: Style,
+    pub fifo: Style,
+    pub socket: Style,
+    // Block and character devices
+    pub device: Style,
+    // Style per lowercase extension, from the categories and the
+    // [extensions] table
+    extensions: HashMap<String, Style>,
+}
+
+// Extensions making up each category of the theme file
+const CATEGORIES: [(&str, &[&str]); 4] = [
+    ("image", &["svg", "png", "jpg"]),
+    ("document", &["pdf"]),
+    ("config", &["yaml", "yml"]),
+    ("archive", &["zip", "tar"]),
+];
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let mut theme = Theme {
+            directory: Style {
+                bold: true,
+                ..Style::fg(Color::Blue)
+            },
+            symlink: Style {
+                italic: true,
+                ..Style::fg(Color::Cyan)
+            },
+            link_target: Style {
+                italic: true,
+                ..Style::fg(Color::Blue)
+            },
+            executable: Style::fg(Color::Green),
+            file: Style::default(),
+            // As dircolors has them
+            fifo: Style::fg(Color::Yellow),
+            socket: Style {
+                bold: true,
+                ..Style::fg(Color::Magenta)
+            },
+            device: Style {
+                bold: true,
+                ..Style::fg(Color::Yellow)
+            },
+            extensions: HashMap::new(),
+        };
+        let colors = [Color::Magenta, Color::Red, Color::Yellow, Color::Red];
+        for ((_, extensions), color) in CATEGORIES.iter().zip(colors) {
+            for ext in extensions.iter() {
+                theme.extensions.insert(ext.to_string(), Style::fg(color));
+            }
+        }
+        theme
+    }
+}
+
+impl Theme {
+    // Style of a regular file called `name`
+    pub fn file_style(&self, name: &str) -> &Style {
+        name.rsplit_once('.')
+            .and_then(|(_, ext)| self.extensions.get(&ext.to_lowercase()))
+            .unwrap_or(&self.file)
+    }
+}
+
+// The file as written
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTheme {
+    directory: Option<Spanned<String>>,
+    symlink: Option<Spanned<String>>,
+    link_target: Option<Spanned<String>>,
+    executable: Option<Spanned<String>>,
+    file: Option<Spanned<String>>,
+    fifo: Option<Spanned<String>>,
+    socket: Option<Spanned<String>>,
+    device: Option<Spanned<String>>,
+    #[serde(default)]
+    categories: BTreeMap<String, Spanned<String>>,
+    #[serde(default)]
+    extensions: BTreeMap<String, Spanned<String>>,
+}
+
+// Read a theme file. Anything it leaves out keeps the built-in style.
+pub fn load(path: &Path) -> Result<Theme, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}:{}", path.display(), e))
+}
+
+// Errors start with the line they were found on
+fn parse(text: &str) -> Result<Theme, String> {
+    let raw: RawTheme = toml::from_str(text).map_err(|e| {
+        let line = e.span().map_or(1, |span| line_of(text, span.start));
+        format!("{}: {}", line, e.message())
+    })?;
+    let style = |spec: &Spanned<String>| {
+        Style::parse(spec.get_ref())
+            .map_err(|e| format!("{}: {}", line_of(text, spec.span().start), e))
+    };
+
+    let mut theme = Theme::default();
+    let kinds = [
+        (&raw.directory, &mut theme.directory),
+        (&raw.symlink, &mut theme.symlink),
+        (&raw.link_target, &mut theme.link_target),
+        (&raw.executable, &mut theme.executable),
+        (&raw.file, &mut theme.file),
+        (&raw.fifo, &mut theme.fifo),
+        (&raw.socket, &mut theme.socket),
+        (&raw.device, &mut theme.device),
+    ];
+    for (spec, kind) in kinds {
+        if let Some(spec) = spec {
+            *kind = style(spec)?;
+        }
+    }
+    for (category, spec) in &raw.categories {
+        let extensions = CATEGORIES
+            .iter()
+            .find(|(name, _)| name == category)
+            .map(|(_, extensions)| *extensions)
+            .ok_or_else(|| {
+                format!(
+                    "{}: unknown category '{}', expected image, document, config or archive",
+                    line_of(text, spec.span().start),
+                    category
+                )
+            })?;
+        let style = style(spec)?;
+        for ext in extensions {
+            theme.extensions.insert(ext.to_string(), style.clone());
+        }
+    }
+    // Single extensions win over their category
+    for (ext, spec) in &raw.extensions {
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        theme.extensions.insert(ext, style(spec)?);
+    }
+
+    Ok(theme)
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+static BUILT_IN: OnceLock<Theme> = OnceLock::new();
+
+pub fn init(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+// Whether a theme was given, which then takes over from LS_COLORS
+pub fn is_custom() -> bool {
+    THEME.get().is_some()
+}
+
+pub fn get() -> &'static Theme {
+    THEME
+        .get()
+        .unwrap_or_else(|| BUILT_IN.get_or_init(Theme::default))
+}
@@ -0,0 +1,193 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::time::SystemTime;
+
+use crate::i18n;
+
+// Parse sizes like `512`, `10K`, `1.5M` or `2GiB`. Suffixes are binary
+// multiples, as in GNU tree and du.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let (number, suffix) = split_number(s);
+    let multiplier: u64 = match suffix.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => return Err(format!("invalid size '{}'", s)),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", s))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+// Parse durations like `30s`, `15m`, `2h`, `3d` or `1w` into seconds
+pub fn parse_duration(s: &str) -> Result<u64, String> {
+    let (number, suffix) = split_number(s);
+    let multiplier: u64 = match suffix {
+        "" | "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return Err(format!("invalid duration '{}'", s)),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", s))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+// Parse a point in time for --newer-than and --older-than: a duration
+// back from now (`2d`), a date (`2024-03-01`, local midnight) or a date
+// and time (`2024-03-01 14:05`, or RFC 3339)
+pub fn parse_time_point(s: &str) -> Result<SystemTime, String> {
+    if let Ok(seconds) = parse_duration(s) {
+        return SystemTime::now()
+            .checked_sub(std::time::Duration::from_secs(seconds))
+            .ok_or_else(|| format!("duration '{}' reaches too far back", s));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.into());
+    }
+    let local = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_time(Default::default()))
+        });
+    match local
+        .ok()
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+    {
+        Some(time) => Ok(time.into()),
+        None => Err(format!("invalid duration or date '{}'", s)),
+    }
+}
+
+fn split_number(s: &str) -> (&str, &str) {
+    let s = s.trim();
+    let idx = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    (&s[..idx], &s[idx..])
+}
+
+// Format a byte count with binary units, e.g. `2.1 GiB` (`2,1 GiB` in
+// German)
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{} {}", decimal(size, 1), UNITS[unit])
+    }
+}
+
+// Format a number with `places` decimals and the locale's decimal point
+pub fn decimal(value: f64, places: usize) -> String {
+    format!("{:.*}", places, value).replace('.', &i18n::decimal_point().to_string())
+}
+
+// Group digits in thousands, e.g. `1,204` (`1.204` in German)
+pub fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(i18n::thousands_separator());
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+// Format a timestamp in local time, e.g. `2024-03-01 14:05:09` (with
+// the day first in most other languages)
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time)
+        .format(i18n::date_format())
+        .to_string()
+}
+
+// Check a strftime format for --timefmt, which chrono would otherwise
+// only trip over while printing
+pub fn parse_time_format(format: &str) -> Result<String, String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("invalid time format '{}'", format));
+    }
+    Ok(format.to_string())
+}
+
+// Format a modification time as GNU tree -D does: the time of day for
+// the last six months, the year for anything older or in the future,
+// or `format` when given. The month is named in the language of LC_TIME.
+pub fn format_mtime(time: SystemTime, format: Option<&str>) -> String {
+    let time = DateTime::<Local>::from(time);
+    if let Some(format) = format {
+        return time.format(format).to_string();
+    }
+    let age = Local::now() - time;
+    let rest = if age < Duration::days(182) && age > Duration::zero() {
+        "%e %H:%M"
+    } else {
+        "%e  %Y"
+    };
+    format!("{} {}", i18n::month_abbr(time.month()), time.format(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("512b"), Ok(512));
+        assert_eq!(parse_size("10K"), Ok(10 * 1024));
+        assert_eq!(parse_size("1.5M"), Ok(3 << 19));
+        assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_size(" 1tb "), Ok(1 << 40));
+        for size in ["", "M", "10X", "1.2.3K", "-1"] {
+            assert!(parse_size(size).is_err(), "{}", size);
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30"), Ok(30));
+        assert_eq!(parse_duration("30s"), Ok(30));
+        assert_eq!(parse_duration("15m"), Ok(15 * 60));
+        assert_eq!(parse_duration("15min"), Ok(15 * 60));
+        assert_eq!(parse_duration("1.5h"), Ok(90 * 60));
+        assert_eq!(parse_duration("3d"), Ok(3 * 24 * 60 * 60));
+        assert_eq!(parse_duration("1w"), Ok(7 * 24 * 60 * 60));
+        assert_eq!(parse_duration("1y"), Ok(365 * 24 * 60 * 60));
+        // Units are case-sensitive: M is never minutes
+        for duration in ["", "d", "2D", "5M", "1x"] {
+            assert!(parse_duration(duration).is_err(), "{}", duration);
+        }
+    }
+
+    #[test]
+    fn parses_time_points() {
+        let point = parse_time_point("2h").unwrap();
+        let ago = SystemTime::now().duration_since(point).unwrap();
+        assert!(ago.as_secs() >= 2 * 60 * 60);
+        assert_eq!(
+            parse_time_point("2024-03-01T12:00:00Z"),
+            Ok(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_709_294_400))
+        );
+        assert!(parse_time_point("2024-03-01").is_ok());
+        assert!(parse_time_point("2024-03-01 08:30").is_ok());
+        assert!(parse_time_point("99999999999999999y").is_err());
+        assert!(parse_time_point("yesterday").is_err());
+    }
+}
@@ -0,0 +1,678 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rayon::prelude::*;
+
+use crate::errors::{self, TraversalError};
+use crate::i18n::{self, Msg};
+use crate::{columns, filetype, fsinfo, names, platform, sort, Context, Opt, SampleMode};
+
+/// An entry that survived filtering, along with everything below it
+pub struct Node {
+    pub path: PathBuf,
+    pub name: String,
+    pub metadata: fs::Metadata,
+    pub depth: usize,
+    pub children: Vec<Node>,
+    /// Entries of this directory left out by --sample
+    pub omitted: usize,
+    /// A symlink to a directory that --follow descended into
+    pub followed: bool,
+    /// Why a directory wasn't descended into, shown next to its name
+    pub badge: Option<String>,
+    /// What was left out below a directory cut off by --max-depth
+    pub rollup: Option<Rollup>,
+    /// Why a directory couldn't be listed, e.g. "Permission denied"
+    pub error: Option<String>,
+    /// Bytes used by a directory and everything in it (with --du)
+    pub du: Option<u64>,
+    /// Lines of a text file, or of all text files below a directory
+    /// (with --lines)
+    pub lines: Option<u64>,
+}
+
+/// What's below a directory cut off by --max-depth
+#[derive(Default, Clone, Copy)]
+pub struct Rollup {
+    pub directories: usize,
+    pub files: usize,
+    pub bytes: u64,
+    /// Lines of the text files, counted with --lines
+    pub lines: u64,
+}
+
+impl Rollup {
+    pub fn add(&mut self, other: &Rollup) {
+        self.directories += other.directories;
+        self.files += other.files;
+        self.bytes += other.bytes;
+        self.lines += other.lines;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directories == 0 && self.files == 0
+    }
+}
+
+impl Node {
+    pub fn is_dir(&self) -> bool {
+        self.metadata.is_dir()
+    }
+
+    // Bytes taken by the entry, counting what's inside directories
+    // with --du
+    pub fn size(&self) -> u64 {
+        self.du.unwrap_or(self.metadata.len())
+    }
+}
+
+// Lines in the text files among `nodes` and below them
+pub fn total_lines(nodes: &[Node]) -> u64 {
+    nodes.iter().filter_map(|node| node.lines).sum()
+}
+
+// Bytes taken by `nodes` and everything below them
+pub fn total_size(nodes: &[Node]) -> u64 {
+    nodes.iter().map(Node::size).sum()
+}
+
+// Directories met so far, keyed by (dev, inode)
+#[derive(Default, Clone)]
+pub struct Seen {
+    // The directories leading up to the one being read
+    pub ancestors: Vec<(u64, u64)>,
+    // Where each directory was first listed, shared by the branches of
+    // a parallel walk
+    pub shown: Arc<Mutex<HashMap<(u64, u64), PathBuf>>>,
+}
+
+impl Seen {
+    // Starting from `dir`, as its first ancestor
+    fn at(dir: &Path) -> Seen {
+        let mut seen = Seen::default();
+        if let Ok(metadata) = fs::metadata(dir) {
+            let id = platform::file_id(dir, &metadata);
+            seen.ancestors.push(id);
+            seen.shown.lock().unwrap().insert(id, dir.to_path_buf());
+        }
+        seen
+    }
+}
+
+// Read the tree below the root `dir`, the way build_tree does
+pub fn walk(dir: &Path, opt: &Opt, ctx: &Context) -> io::Result<(Vec<Node>, usize)> {
+    build_tree(dir, 1, opt, ctx, &mut Seen::at(dir))
+}
+
+// The entries of `dir` alone, for browsing a level at a time. The
+// filters apply, but directories come without their children, unless
+// --prune, --min-depth or --query need them to tell whether a directory
+// stays: then the whole tree below `dir` is walked, children included.
+pub fn list_dir(dir: &Path, depth: usize, opt: &Opt, ctx: &Context) -> io::Result<Vec<Node>> {
+    if opt.max_depth.is_some_and(|max_depth| depth > max_depth) {
+        return Ok(Vec::new());
+    }
+    if opt.prune || opt.min_depth.is_some() || opt.query.is_some() {
+        let (nodes, _) = build_tree(dir, depth, opt, ctx, &mut Seen::at(dir))?;
+        return Ok(nodes);
+    }
+
+    let mut nodes = Vec::new();
+    for entry in read_entries(dir, opt, ctx)? {
+        let path = entry.path();
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(m) => m,
+            Err(e) => {
+                ctx.record(TraversalError::io(&path, &e));
+                continue;
+            }
+        };
+        let followed = opt.follow && metadata.file_type().is_symlink() && path.is_dir();
+        nodes.push(Node {
+            name: names::display(path.file_name().unwrap(), opt.escape),
+            path,
+            metadata,
+            depth,
+            children: Vec::new(),
+            omitted: 0,
+            followed,
+            badge: None,
+            rollup: None,
+            error: None,
+            du: None,
+            lines: None,
+        });
+    }
+    Ok(nodes)
+}
+
+// An entry that passed the filters, with what the walk decided to do
+// about it, before anything below it is read
+struct Pending {
+    path: PathBuf,
+    metadata: fs::Metadata,
+    id: (u64, u64),
+    descend: bool,
+    followed: bool,
+    badge: Option<String>,
+}
+
+// Read the tree below `dir`. Entries are at `depth` (1 for the children
+// of the root). Directories that don't match the query, or sit above
+// --min-depth, are kept only when something inside them does. Also returns how many entries were
+// left out by --sample, or why `dir` couldn't be read.
+//
+// `seen` tracks directories by (dev, inode), so neither followed links
+// nor bind mounts make the walk go around in circles or list a subtree
+// twice.
+//
+// With --parallel the subdirectories are read on rayon's thread pool.
+// The entries keep their order, but which of two paths to the same
+// directory counts as the first one can vary from run to run.
+pub fn build_tree(
+    dir: &Path,
+    depth: usize,
+    opt: &Opt,
+    ctx: &Context,
+    seen: &mut Seen,
+) -> io::Result<(Vec<Node>, usize)> {
+    if let Some(max_depth) = opt.max_depth {
+        if depth > max_depth {
+            return Ok((Vec::new(), 0));
+        }
+    }
+
+    let mut entries = read_entries(dir, opt, ctx)?;
+    let mut omitted = 0;
+    if let Some(limit) = opt.sample {
+        omitted = entries.len().saturating_sub(limit);
+        sample(&mut entries, limit, opt.sample_mode);
+    }
+
+    let mut nodes: Vec<Node> = if opt.parallel {
+        let pending: Vec<Pending> = entries
+            .iter()
+            .filter_map(|entry| prepare(entry, opt, ctx, seen))
+            .collect();
+        pending
+            .into_par_iter()
+            .filter_map(|pending| finish(pending, depth, opt, ctx, &mut seen.clone()))
+            .collect()
+    } else {
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let pending = prepare(entry, opt, ctx, seen)?;
+                finish(pending, depth, opt, ctx, seen)
+            })
+            .collect()
+    };
+    sort::sort_nodes(&mut nodes, opt);
+
+    Ok((nodes, omitted))
+}
+
+// Stat an entry and decide whether to descend into it
+fn prepare(entry: &fs::DirEntry, opt: &Opt, ctx: &Context, seen: &Seen) -> Option<Pending> {
+    let path = entry.path();
+
+    // Get metadata
+    let metadata = match fs::symlink_metadata(&path) {
+        Ok(m) => m,
+        Err(e) => {
+            ctx.record(TraversalError::io(&path, &e));
+            return None;
+        }
+    };
+
+    // Directories, and with --follow links to them, are descended into
+    let mut id = platform::file_id(&path, &metadata);
+    let mut descend = metadata.is_dir();
+    let mut followed = false;
+    let mut badge = None;
+    if opt.follow && metadata.file_type().is_symlink() {
+        if let Ok(target) = fs::metadata(&path) {
+            if target.is_dir() {
+                id = platform::file_id(&path, &target);
+                if seen.ancestors.contains(&id) {
+                    badge = Some(i18n::tr(Msg::Recursive).to_string());
+                } else {
+                    followed = true;
+                    descend = true;
+                }
+            }
+        }
+    }
+    if descend && opt.safe && fsinfo::is_virtual(&path) {
+        badge = Some(i18n::tr(Msg::VirtualFs).to_string());
+        descend = false;
+    }
+    if opt.mounts && (metadata.is_dir() || followed) {
+        let parent_dev = seen.ancestors.last().map(|&(dev, _)| dev);
+        if parent_dev.is_some_and(|dev| dev != id.0) {
+            badge = Some(mount_badge(&path, ctx, badge));
+        }
+    }
+    // The root is the first ancestor
+    if descend && opt.one_file_system {
+        let root_dev = seen.ancestors.first().map(|&(dev, _)| dev);
+        if root_dev.is_some_and(|dev| dev != id.0) {
+            descend = false;
+        }
+    }
+    if let Some(limit) = opt.filelimit.filter(|_| descend) {
+        let count = fs::read_dir(&path).map_or(0, |entries| entries.count());
+        if count > limit {
+            let note = i18n::fill(Msg::FileLimit, &[&count]);
+            badge = Some(match badge {
+                Some(badge) => format!("{} {}", badge, note),
+                None => note,
+            });
+            descend = false;
+        }
+    }
+    if descend {
+        let mut shown = seen.shown.lock().unwrap();
+        if let Some(first) = shown.get(&id) {
+            let note = i18n::fill(Msg::AlreadyShown, &[&first.display()]);
+            badge = Some(match badge {
+                Some(badge) => format!("{} {}", badge, note),
+                None => note,
+            });
+            descend = false;
+        } else {
+            shown.insert(id, path.clone());
+        }
+    }
+
+    Some(Pending {
+        path,
+        metadata,
+        id,
+        descend,
+        followed,
+        badge,
+    })
+}
+
+// Read what's below a prepared entry and make its node, None when the
+// filters leave it out
+fn finish(
+    pending: Pending,
+    depth: usize,
+    opt: &Opt,
+    ctx: &Context,
+    seen: &mut Seen,
+) -> Option<Node> {
+    let Pending {
+        path,
+        metadata,
+        id,
+        descend,
+        followed,
+        badge,
+    } = pending;
+
+    let mut rollup = None;
+    let mut error = None;
+    let (children, child_omitted) = if !descend {
+        (Vec::new(), 0)
+    } else if opt.max_depth == Some(depth) {
+        // Children would be cut off, summarize them instead
+        if opt.rollup || opt.du || opt.lines {
+            let mut summary = Rollup::default();
+            seen.ancestors.push(id);
+            count_tree(&path, opt, ctx, seen, &mut summary);
+            seen.ancestors.pop();
+            rollup = Some(summary);
+        }
+        (Vec::new(), 0)
+    } else {
+        seen.ancestors.push(id);
+        let listing = build_tree(&path, depth + 1, opt, ctx, seen);
+        seen.ancestors.pop();
+        listing.unwrap_or_else(|e| {
+            error = Some(errors::describe(&e));
+            (Vec::new(), 0)
+        })
+    };
+
+    // Directories cut off by the depth limit still count what's in them,
+    // both for --du and --lines
+    let du = (opt.du && descend).then(|| {
+        let below = rollup.as_ref().map_or(0, |rollup| rollup.bytes);
+        metadata.len() + total_size(&children) + below
+    });
+    let lines = match opt.lines {
+        false => None,
+        true if descend => {
+            let below = rollup.as_ref().map_or(0, |rollup| rollup.lines);
+            Some(total_lines(&children) + below)
+        }
+        true if metadata.is_file() => filetype::line_count(&path),
+        true => None,
+    };
+    if !opt.rollup {
+        rollup = None;
+    }
+
+    let node = Node {
+        name: names::display(path.file_name().unwrap(), opt.escape),
+        path,
+        metadata,
+        depth,
+        children,
+        omitted: child_omitted,
+        followed,
+        badge,
+        rollup,
+        error,
+        du,
+        lines,
+    };
+
+    // Directories with nothing left after filtering. Those cut off by
+    // --max-depth weren't read, and those that couldn't be read show
+    // why, so they stay.
+    let read = descend && opt.max_depth != Some(depth) && node.error.is_none();
+    if opt.prune && read && node.children.is_empty() {
+        return None;
+    }
+    if opt.min_depth.is_some_and(|min| depth < min) && node.children.is_empty() {
+        return None;
+    }
+    if let Some(query) = &ctx.query {
+        if node.children.is_empty() && !query.matches(&node, &ctx.root) {
+            return None;
+        }
+    }
+
+    Some(node)
+}
+
+// A directory on another device than its parent is a mount point
+fn mount_badge(path: &Path, ctx: &Context, badge: Option<String>) -> String {
+    let fs_type = fs::canonicalize(path)
+        .ok()
+        .and_then(|canonical| ctx.mount_types.get(&canonical));
+    let mount = match fs_type {
+        Some(fs_type) => i18n::fill(Msg::MountOf, &[fs_type]),
+        None => i18n::tr(Msg::Mount).to_string(),
+    };
+    match badge {
+        Some(badge) => format!("{} {}", mount, badge),
+        None => mount,
+    }
+}
+
+// Keep `limit` entries, preserving their order
+fn sample<T>(entries: &mut Vec<T>, limit: usize, mode: SampleMode) {
+    if entries.len() <= limit {
+        return;
+    }
+    match mode {
+        SampleMode::First => entries.truncate(limit),
+        SampleMode::Last => {
+            entries.drain(..entries.len() - limit);
+        }
+        SampleMode::Random => {
+            // Partial Fisher-Yates over the indices, seeded per run
+            let mut seed = RandomState::new().build_hasher().finish() | 1;
+            let mut indices: Vec<usize> = (0..entries.len()).collect();
+            for i in 0..limit {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let j = i + (seed % (indices.len() - i) as u64) as usize;
+                indices.swap(i, j);
+            }
+            let mut keep = vec![false; entries.len()];
+            for &i in &indices[..limit] {
+                keep[i] = true;
+            }
+            let mut keep = keep.into_iter();
+            entries.retain(|_| keep.next().unwrap_or(false));
+        }
+    }
+}
+
+// Tally everything below `dir` without building nodes. Entries go
+// through prepare like in build_tree, so what the walk wouldn't descend
+// into (other filesystems with -x, virtual ones with --safe, directories
+// already met) isn't counted either.
+fn count_tree(dir: &Path, opt: &Opt, ctx: &Context, seen: &mut Seen, rollup: &mut Rollup) {
+    for entry in read_entries(dir, opt, ctx).unwrap_or_default() {
+        let pending = match prepare(&entry, opt, ctx, seen) {
+            Some(pending) => pending,
+            None => continue,
+        };
+        // Like --du in a full walk, directories count their own size
+        // too. Followed links count as the directory they lead to.
+        rollup.bytes += pending.metadata.len();
+        if pending.metadata.is_dir() || pending.followed {
+            rollup.directories += 1;
+        } else {
+            rollup.files += 1;
+            if opt.lines && pending.metadata.is_file() {
+                rollup.lines += filetype::line_count(&pending.path).unwrap_or(0);
+            }
+        }
+        if pending.descend {
+            seen.ancestors.push(pending.id);
+            count_tree(&pending.path, opt, ctx, seen, rollup);
+            seen.ancestors.pop();
+        }
+    }
+}
+
+// Read, sort and filter the entries of `dir`. A directory that can't
+// be opened is recorded as an error and returned as one.
+pub fn read_entries(dir: &Path, opt: &Opt, ctx: &Context) -> io::Result<Vec<fs::DirEntry>> {
+    let entries_iter = match fs::read_dir(dir) {
+        Ok(iter) => iter,
+        Err(e) => {
+            ctx.record(TraversalError::io(dir, &e));
+            return Err(e);
+        }
+    };
+    let mut entries: Vec<_> = entries_iter
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                ctx.record(TraversalError::io(dir, &e));
+                None
+            }
+        })
+        .collect();
+    sort::sort_entries(&mut entries, opt);
+
+    // Filter entries after sorting
+    Ok(entries
+        .into_iter()
+        .filter(|entry| {
+            let path = entry.path();
+            let file_name = entry.file_name();
+
+            if !opt.show_hidden && platform::is_hidden(entry) {
+                return false;
+            }
+            // Links count as directories when they lead to one
+            if opt.dirs_only && !path.is_dir() {
+                return false;
+            }
+
+            // Check if the path matches any ignore pattern
+            if ctx.ignore_patterns.iter().any(|pattern| {
+                // For absolute patterns, match against the full path
+                if pattern.glob().glob().starts_with('/') {
+                    pattern.is_match(&path)
+                } else {
+                    pattern.is_match(&file_name)
+                }
+            }) {
+                return false;
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            if ctx.ignores.is_ignored(&path, is_dir) {
+                return false;
+            }
+            if ctx
+                .ignore_regex
+                .as_ref()
+                .is_some_and(|re| re.is_match(&names::os_bytes(&file_name)))
+            {
+                return false;
+            }
+
+            // Filters picking files; the directories walked into are
+            // listed regardless, leading to what matched
+            let walked = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => opt.follow && path.is_dir(),
+                Ok(file_type) => file_type.is_dir(),
+                Err(_) => false,
+            };
+            if !walked && !wanted(entry, &file_name, opt, ctx) {
+                return false;
+            }
+
+            true
+        })
+        .collect())
+}
+
+// Whether a file called `name` passes the include filters
+fn wanted(entry: &fs::DirEntry, name: &OsStr, opt: &Opt, ctx: &Context) -> bool {
+    let patterns = &ctx.match_patterns;
+    if !patterns.is_empty() && !patterns.iter().any(|p| p.is_match(name)) {
+        return false;
+    }
+    let bytes = names::os_bytes(name);
+    if ctx
+        .match_regex
+        .as_ref()
+        .is_some_and(|re| !re.is_match(&bytes))
+    {
+        return false;
+    }
+    if !opt.ext.is_empty() {
+        // A leading dot starts a hidden name, not an extension
+        let stem_start = bytes.iter().position(|&b| b != b'.').unwrap_or(bytes.len());
+        let ext = match bytes[stem_start..].iter().rposition(|&b| b == b'.') {
+            Some(dot) => &bytes[stem_start + dot + 1..],
+            None => return false,
+        };
+        let listed = opt.ext.iter().map(|e| e.trim_start_matches('.')).any(|e| {
+            if opt.ignore_case {
+                e.as_bytes().eq_ignore_ascii_case(ext)
+            } else {
+                e.as_bytes() == ext
+            }
+        });
+        if !listed {
+            return false;
+        }
+    }
+
+    if opt.min_size.is_some() || opt.max_size.is_some() {
+        let size = match entry.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return false,
+        };
+        if opt.min_size.is_some_and(|min| size < min) || opt.max_size.is_some_and(|max| size > max)
+        {
+            return false;
+        }
+    }
+
+    if !opt.types.is_empty() {
+        let letter = match entry.file_type() {
+            Ok(file_type) => columns::type_char(file_type),
+            Err(_) => return false,
+        };
+        if !opt.types.iter().any(|t| t.letter() == letter) {
+            return false;
+        }
+    }
+
+    if opt.newer_than.is_some() || opt.older_than.is_some() {
+        let modified = match entry.metadata().and_then(|metadata| metadata.modified()) {
+            Ok(time) => time,
+            Err(_) => return false,
+        };
+        if opt.newer_than.is_some_and(|since| modified < since)
+            || opt.older_than.is_some_and(|before| modified >= before)
+        {
+            return false;
+        }
+    }
+    true
+}
+
+// The trees below use symlinks
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::build_context;
+    use clap::Parser;
+    use std::os::unix::fs::symlink;
+
+    // The --du total of the tree at `root`, with `args`
+    fn du(root: &Path, args: &[&str]) -> u64 {
+        let root_arg = root.to_str().unwrap();
+        let opt = Opt::parse_from(["rtree", root_arg, "--du"].iter().chain(args));
+        let ctx = build_context(root, &opt);
+        let (nodes, _) = walk(root, &opt, &ctx).unwrap();
+        total_size(&nodes)
+    }
+
+    #[test]
+    fn depth_limit_keeps_du_total() {
+        let root = std::env::temp_dir().join(format!("rtree-walk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::create_dir_all(root.join("d")).unwrap();
+        fs::write(root.join("a/one"), "1").unwrap();
+        fs::write(root.join("a/b/c/three"), "333").unwrap();
+        fs::write(root.join("d/four"), "4444").unwrap();
+        // A loop, and a second way to reach d, each walked only once
+        symlink("..", root.join("a/b/up")).unwrap();
+        symlink("../../d", root.join("a/b/d")).unwrap();
+
+        for args in [&[][..], &["--follow"]] {
+            let full = du(&root, args);
+            for depth in ["1", "2"] {
+                let limited = [args, &["-L", depth]].concat();
+                assert_eq!(du(&root, &limited), full, "{:?}", limited);
+            }
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // What's below the depth limit goes through the same checks as the
+    // walk, so --safe doesn't count what's in /proc either
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn depth_limit_skips_virtual_filesystems() {
+        let root = std::env::temp_dir().join(format!("rtree-safe-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a")).unwrap();
+        symlink("/proc", root.join("a/proc")).unwrap();
+
+        let root_arg = root.to_str().unwrap();
+        let args = [
+            "rtree", root_arg, "--follow", "--safe", "--rollup", "-L", "1",
+        ];
+        let opt = Opt::parse_from(args);
+        let ctx = build_context(&root, &opt);
+        let (nodes, _) = walk(&root, &opt, &ctx).unwrap();
+        let rollup = nodes[0].rollup.unwrap();
+        assert_eq!((rollup.directories, rollup.files), (1, 0));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::errors::{self, TraversalError};
+use crate::walk::Node;
+use crate::{build_context, platform, read_root, render_walked, Opt, Walked};
+
+// How long a change stays highlighted
+const HIGHLIGHT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change {
+    Added,
+    Modified,
+}
+
+// Entries changed lately, for list_contents to mark
+static CHANGES: Mutex<Option<HashMap<PathBuf, Change>>> = Mutex::new(None);
+
+// How `path` changed lately, if it did
+pub fn change_of(path: &Path) -> Option<Change> {
+    CHANGES.lock().unwrap().as_ref()?.get(path).copied()
+}
+
+// What an entry looked like at the last walk
+#[derive(PartialEq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    is_dir: bool,
+}
+
+// Redraw the tree whenever something in it changes (--watch), until
+// interrupted. Changes are noticed through inotify on Linux and by
+// walking the tree every second elsewhere. Each walk is both compared
+// with the last one and rendered, and what couldn't be read is reported
+// once, when first met.
+pub fn run(opt: &Opt) -> io::Result<()> {
+    // Watching starts before the first walk, so nothing that changes
+    // while the tree is read goes unnoticed
+    let mut watcher = Watcher::new(opt);
+    let mut reported = HashSet::new();
+    let walks = walk_trees(opt);
+    let mut snapshot = take_snapshot(&walks);
+    let errors = new_errors(&walks, &mut reported);
+    draw(opt, walks)?;
+    errors::emit(&errors, opt.errors);
+    // When each change was seen
+    let mut changes: HashMap<PathBuf, (Change, Instant)> = HashMap::new();
+
+    loop {
+        // Directories only get watched once they've been walked, so
+        // what changed in new ones meanwhile needs another walk
+        if !watcher.watch(&snapshot) {
+            let expiry = changes
+                .values()
+                .map(|&(_, seen)| seen + HIGHLIGHT)
+                .min()
+                .map(|at| at.saturating_duration_since(Instant::now()));
+            watcher.wait(expiry);
+        }
+
+        let walks = walk_trees(opt);
+        let current = take_snapshot(&walks);
+        let now = Instant::now();
+        // Removed entries only show by being gone
+        let mut dirty = snapshot.keys().any(|path| !current.contains_key(path));
+        for (path, stamp) in &current {
+            let change = match snapshot.get(path) {
+                None => Change::Added,
+                // A directory's time changes with its entries, which
+                // show for themselves
+                Some(old) if old != stamp && !stamp.is_dir => Change::Modified,
+                _ => continue,
+            };
+            changes.insert(path.clone(), (change, now));
+            dirty = true;
+        }
+        let before = changes.len();
+        changes.retain(|path, &mut (_, seen)| {
+            current.contains_key(path) && now.duration_since(seen) < HIGHLIGHT
+        });
+        dirty |= changes.len() != before;
+
+        let errors = new_errors(&walks, &mut reported);
+        if dirty {
+            *CHANGES.lock().unwrap() = Some(
+                changes
+                    .iter()
+                    .map(|(path, &(change, _))| (path.clone(), change))
+                    .collect(),
+            );
+            draw(opt, walks)?;
+        }
+        // After the frame, which would clear them
+        errors::emit(&errors, opt.errors);
+        snapshot = current;
+    }
+}
+
+// Walk every tree, keeping what couldn't be read for new_errors rather
+// than reporting it, and going on past it even with --strict
+fn walk_trees(opt: &Opt) -> VecDeque<Walked> {
+    opt.paths
+        .iter()
+        .map(|path| walk_tree(&platform::long_path(path), opt))
+        .collect()
+}
+
+fn walk_tree(root: &Path, opt: &Opt) -> Walked {
+    let mut ctx = build_context(root, opt);
+    ctx.fail_fast = None;
+    read_root(root, opt, ctx)
+}
+
+// What couldn't be read in `walks` that wasn't in an earlier walk
+fn new_errors(
+    walks: &VecDeque<Walked>,
+    reported: &mut HashSet<(PathBuf, String)>,
+) -> Vec<TraversalError> {
+    let mut errors = Vec::new();
+    for (ctx, ..) in walks {
+        for error in ctx.errors.lock().unwrap().iter() {
+            if reported.insert((error.path.clone(), error.message.clone())) {
+                errors.push(error.clone());
+            }
+        }
+    }
+    errors
+}
+
+// Clear the screen and render the walked trees, all at once so it
+// doesn't flicker
+fn draw(opt: &Opt, mut walks: VecDeque<Walked>) -> io::Result<()> {
+    let mut frame = b"\x1b[H\x1b[2J".to_vec();
+    // The walks come in the order of the paths, as render asks for them
+    let mut walker = |path: &Path| walks.pop_front().unwrap_or_else(|| walk_tree(path, opt));
+    render_walked(opt, &mut walker, &mut frame)?;
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&frame)?;
+    stdout.flush()
+}
+
+// Every entry of the trees with what it looked like
+fn take_snapshot(walks: &VecDeque<Walked>) -> HashMap<PathBuf, Stamp> {
+    let mut snapshot = HashMap::new();
+    for (_, nodes, ..) in walks {
+        add_nodes(nodes, &mut snapshot);
+    }
+    snapshot
+}
+
+fn add_nodes(nodes: &[Node], snapshot: &mut HashMap<PathBuf, Stamp>) {
+    for node in nodes {
+        snapshot.insert(
+            node.path.clone(),
+            Stamp {
+                len: node.metadata.len(),
+                modified: node.metadata.modified().ok(),
+                is_dir: node.is_dir() || node.followed,
+            },
+        );
+        add_nodes(&node.children, snapshot);
+    }
+}
+
+// Tells when something changes in the directories of the trees
+struct Watcher {
+    // The inotify instance, None where there's none and the tree is
+    // walked every second instead
+    #[cfg(target_os = "linux")]
+    fd: Option<i32>,
+    // The directories of the roots, watched throughout
+    #[cfg(target_os = "linux")]
+    roots: Vec<PathBuf>,
+    // Watch descriptor of each directory, negative where it couldn't be
+    // watched
+    #[cfg(target_os = "linux")]
+    watched: HashMap<PathBuf, i32>,
+}
+
+#[cfg(target_os = "linux")]
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MODIFY
+    | libc::IN_ATTRIB
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF;
+
+#[cfg(target_os = "linux")]
+impl Watcher {
+    // Watching the roots already, and the directories holding roots
+    // that are files
+    fn new(opt: &Opt) -> Watcher {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        let mut watcher = Watcher {
+            fd: (fd >= 0).then_some(fd),
+            roots: Vec::new(),
+            watched: HashMap::new(),
+        };
+        for path in &opt.paths {
+            let root = platform::long_path(path);
+            let dir = match root.parent() {
+                Some(parent) if !root.is_dir() => parent.to_path_buf(),
+                _ => root,
+            };
+            watcher.add(dir.clone());
+            watcher.roots.push(dir);
+        }
+        watcher
+    }
+
+    // Watch the directories of `snapshot` that aren't yet, and stop
+    // watching those that are gone. Returns whether any were added.
+    fn watch(&mut self, snapshot: &HashMap<PathBuf, Stamp>) -> bool {
+        let Some(fd) = self.fd else {
+            return false;
+        };
+        let gone: Vec<PathBuf> = self
+            .watched
+            .keys()
+            .filter(|dir| !self.roots.contains(dir))
+            .filter(|dir| !snapshot.get(*dir).is_some_and(|stamp| stamp.is_dir))
+            .cloned()
+            .collect();
+        // Before adding any, as a directory moved within the tree keeps
+        // its watch descriptor
+        for dir in gone {
+            if let Some(wd) = self.watched.remove(&dir).filter(|&wd| wd >= 0) {
+                unsafe { libc::inotify_rm_watch(fd, wd) };
+            }
+        }
+
+        let new: Vec<PathBuf> = snapshot
+            .iter()
+            .filter(|(path, stamp)| stamp.is_dir && !self.watched.contains_key(*path))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let added = !new.is_empty();
+        for dir in new {
+            self.add(dir);
+        }
+        added
+    }
+
+    fn add(&mut self, dir: PathBuf) {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let Some(fd) = self.fd else {
+            return;
+        };
+        let wd = match CString::new(dir.as_os_str().as_bytes()) {
+            Ok(c_dir) => unsafe { libc::inotify_add_watch(fd, c_dir.as_ptr(), MASK) },
+            Err(_) => -1,
+        };
+        self.watched.insert(dir, wd);
+    }
+
+    // Block until something changes, or `timeout` runs out
+    fn wait(&self, timeout: Option<Duration>) {
+        let Some(fd) = self.fd else {
+            let second = Duration::from_secs(1);
+            std::thread::sleep(timeout.map_or(second, |t| t.min(second)));
+            return;
+        };
+        let poll = |timeout: Option<Duration>| {
+            let mut pollfd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let millis = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as i32);
+            unsafe { libc::poll(&mut pollfd, 1, millis) > 0 }
+        };
+        // Let a burst of changes (a build, an unpacked archive) settle
+        // into one redraw
+        if poll(timeout) {
+            while poll(Some(Duration::from_millis(100))) {
+                let mut buf = [0u8; 4096];
+                while unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) }
+                    > 0
+                {}
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        if let Some(fd) = self.fd {
+            unsafe { libc::close(fd) };
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    fn new(_opt: &Opt) -> Watcher {
+        Watcher {}
+    }
+
+    // Every walk sees everything, there's nothing to add
+    fn watch(&mut self, _snapshot: &HashMap<PathBuf, Stamp>) -> bool {
+        false
+    }
+
+    fn wait(&self, timeout: Option<Duration>) {
+        let second = Duration::from_secs(1);
+        std::thread::sleep(timeout.map_or(second, |t| t.min(second)));
+    }
+}
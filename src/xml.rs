@@ -0,0 +1,94 @@
+use std::fs;
+use std::io::{self, Write};
+
+use crate::walk::Node;
+use crate::{json, names, Stats};
+
+// Write the tree in the element layout of GNU tree -X
+pub fn write_tree(
+    root_label: &str,
+    nodes: &[Node],
+    stats: &Stats,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<tree>")?;
+    writeln!(out, "  <directory name=\"{}\">", escape(root_label))?;
+    for node in nodes {
+        write_node(node, 2, out)?;
+    }
+    writeln!(out, "  </directory>")?;
+    writeln!(out, "  <report>")?;
+    writeln!(out, "    <directories>{}</directories>", stats.directories)?;
+    writeln!(out, "    <files>{}</files>", stats.files)?;
+    if let Some(used) = stats.used {
+        writeln!(out, "    <size>{}</size>", used)?;
+    }
+    writeln!(out, "  </report>")?;
+    writeln!(out, "</tree>")
+}
+
+fn write_node(node: &Node, level: usize, out: &mut dyn Write) -> io::Result<()> {
+    let indent = "  ".repeat(level);
+    let name = escape(&node.name);
+
+    if node.metadata.file_type().is_symlink() {
+        let target = fs::read_link(&node.path)
+            .map(|target| names::display(target.as_os_str(), false))
+            .unwrap_or_default();
+        write!(
+            out,
+            "{}<link name=\"{}\" target=\"{}\">",
+            indent,
+            name,
+            escape(&target)
+        )?;
+        if node.followed {
+            writeln!(out)?;
+            write_contents(node, level + 1, out)?;
+            write!(out, "{}", indent)?;
+        }
+        writeln!(out, "</link>")
+    } else if node.is_dir() {
+        writeln!(out, "{}<directory name=\"{}\">", indent, name)?;
+        write_contents(node, level + 1, out)?;
+        writeln!(out, "{}</directory>", indent)
+    } else {
+        let kind = json::kind(node);
+        writeln!(out, "{}<{} name=\"{}\"></{}>", indent, kind, name, kind)
+    }
+}
+
+// The children of a directory, or as in GNU tree an <error> when it
+// couldn't be read
+fn write_contents(node: &Node, level: usize, out: &mut dyn Write) -> io::Result<()> {
+    if let Some(error) = &node.error {
+        let indent = "  ".repeat(level);
+        writeln!(out, "{}<error>{}</error>", indent, escape(error))?;
+    }
+    for child in &node.children {
+        write_node(child, level, out)?;
+    }
+    Ok(())
+}
+
+// `s` made safe for an attribute value or text. Tabs and line breaks
+// are written as references so attributes keep them; other control
+// characters aren't allowed in XML 1.0, not even as references, and
+// become U+FFFD.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push_str(&format!("&#{};", c as u32)),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}